@@ -1,16 +1,38 @@
 'use client';
 
 import { useState } from 'react';
-import { useNoteStore } from '@/lib/store';
+import { useNoteStore, type BatchExportFormat } from '@/lib/store';
 import { Button } from './ui/button';
-import { Pin, PinOff, Trash2, X } from 'lucide-react';
+import { Check, Copy, Pin, PinOff, Trash2, X } from 'lucide-react';
 import { ConfirmDialog } from './confirm-dialog';
+import {
+	DropdownMenu,
+	DropdownMenuCheckboxItem,
+	DropdownMenuContent,
+	DropdownMenuItem,
+	DropdownMenuSeparator,
+	DropdownMenuTrigger,
+} from './ui/dropdown-menu';
+
+const COPY_FORMATS: { format: BatchExportFormat; label: string }[] = [
+	{ format: 'markdown', label: 'Markdown' },
+	{ format: 'text', label: 'テキスト' },
+	{ format: 'json', label: 'JSON' },
+];
 
 export function MultiSelectBar() {
-	const { selectedNodeIds, batchDelete, batchPin, clearSelection } = useNoteStore();
+	const { selectedNodeIds, batchDelete, batchPin, batchCopy, clearSelection } = useNoteStore();
 	const [confirmOpen, setConfirmOpen] = useState(false);
+	const [includeChildren, setIncludeChildren] = useState(false);
+	const [copied, setCopied] = useState(false);
 	const count = selectedNodeIds.size;
 
+	const handleCopy = async (format: BatchExportFormat) => {
+		if (!(await batchCopy(format, includeChildren))) return;
+		setCopied(true);
+		setTimeout(() => setCopied(false), 1200);
+	};
+
 	if (count <= 1) return null;
 
 	return (
@@ -24,6 +46,29 @@ export function MultiSelectBar() {
 				<PinOff className="w-3 h-3" />
 				解除
 			</Button>
+			<DropdownMenu>
+				<DropdownMenuTrigger asChild>
+					<Button variant="ghost" size="sm" className="h-7 px-2 text-xs gap-1">
+						{copied ? <Check className="w-3 h-3" /> : <Copy className="w-3 h-3" />}
+						コピー
+					</Button>
+				</DropdownMenuTrigger>
+				<DropdownMenuContent align="end" className="w-44 [&_[role=menuitem]]:text-xs [&_[role=menuitemcheckbox]]:text-xs">
+					{COPY_FORMATS.map(({ format, label }) => (
+						<DropdownMenuItem key={format} onClick={() => void handleCopy(format)}>
+							{label}としてコピー
+						</DropdownMenuItem>
+					))}
+					<DropdownMenuSeparator />
+					<DropdownMenuCheckboxItem
+						checked={includeChildren}
+						onCheckedChange={(checked) => setIncludeChildren(checked === true)}
+						onSelect={(e) => e.preventDefault()}
+					>
+						子ノートを含める
+					</DropdownMenuCheckboxItem>
+				</DropdownMenuContent>
+			</DropdownMenu>
 			<Button variant="ghost" size="sm" className="h-7 px-2 text-xs gap-1 text-red-500 hover:text-red-500 hover:bg-red-500/10" onClick={() => setConfirmOpen(true)}>
 				<Trash2 className="w-3 h-3" />
 				削除
@@ -39,9 +39,9 @@ export function QuickSwitcher({ isOpen, onClose }: QuickSwitcherProps) {
 		const seq = ++searchSeqRef.current;
 		const timer = setTimeout(async () => {
 			try {
-				const searchResults = await invoke<any[]>('search_notes', { query, limit: 30 });
+				const searchPage = await invoke<{ results: any[] }>('search_notes', { query, limit: 30 });
 				if (searchSeqRef.current !== seq) return;
-				setResults(searchResults);
+				setResults(searchPage.results);
 				setSelectedIndex(0);
 			} catch (error) {
 				if (searchSeqRef.current !== seq) return;
@@ -90,17 +90,13 @@ export function TreeSidebar({ splitMode = 'single' }: { splitMode?: 'single' | '
 				.map((n) => ({ node: n, depth: 0, parentPath: getParentPathLabel(treeNodes, n.id) }));
 		}
 		const traverse = (parentId: string | null, depth: number) => {
+			// treeNodes keep the backend's display order (pins, sort settings) per parent
 			let children = treeNodes.filter((n) => n.parentId === parentId);
 			if (parentId === null && sortMode === 'recent') {
 				children = [...children].sort((a, b) => {
 					if (a.isPinned !== b.isPinned) return a.isPinned ? -1 : 1;
 					return b.updatedAt - a.updatedAt;
 				});
-			} else {
-				children.sort((a, b) => {
-					if (a.isPinned !== b.isPinned) return a.isPinned ? -1 : 1;
-					return a.orderKey - b.orderKey;
-				});
 			}
 			for (const child of children) {
 				nodes.push({ node: child, depth });
@@ -179,12 +175,9 @@ export function TreeSidebar({ splitMode = 'single' }: { splitMode?: 'single' | '
 		if (!overNode) return;
 
 		if (newIndex > oldIndex) {
-			const siblings = treeNodes.filter((n) => n.parentId === overNode.parentId).sort((a, b) => a.orderKey - b.orderKey);
-			const overIndexInSiblings = siblings.findIndex((n) => n.id === overId);
-			const nextSibling = siblings[overIndexInSiblings + 1];
-			moveNote(activeId, overNode.parentId, undefined, nextSibling?.id);
-		} else {
 			moveNote(activeId, overNode.parentId, undefined, overId);
+		} else {
+			moveNote(activeId, overNode.parentId, overId, undefined);
 		}
 	};
 
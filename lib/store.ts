@@ -18,8 +18,21 @@ export interface TreeNode {
 	hasChildren: boolean;
 	createdAt: number;
 	updatedAt: number;
+	structureUpdatedAt: number;
 }
 
+/** Sibling order key, listed in display order by `move_note`, `batch_move` and `get_sibling_order`. */
+export interface OrderKeyUpdate {
+	id: string;
+	orderKey: number;
+}
+
+/** Spacing between order keys, as in the backend's `ordering.rs`. */
+const ORDER_STEP = 1024;
+
+/** Output formats of `batch_export`. */
+export type BatchExportFormat = 'markdown' | 'text' | 'json';
+
 export interface BacklinkNote {
 	id: string;
 	title: string;
@@ -80,6 +93,8 @@ interface NoteStore {
 	deleteNote: (id: string) => Promise<void>;
 	batchDelete: () => Promise<void>;
 	batchPin: (pin: boolean) => Promise<void>;
+	/** Copy the selected notes (with their subtrees when `includeChildren`) in tree order; resolves to whether it worked. */
+	batchCopy: (format: BatchExportFormat, includeChildren: boolean) => Promise<boolean>;
 	moveNote: (noteId: string, newParentId: string | null, beforeId?: string, afterId?: string) => Promise<void>;
 	nestNote: (noteId: string, parentId: string) => Promise<void>;
 	togglePinNote: (id: string) => Promise<void>;
@@ -123,6 +138,7 @@ function mapTreeNode(raw: Record<string, unknown>): TreeNode {
 		hasChildren: (raw.hasChildren ?? raw.has_children ?? false) as boolean,
 		createdAt: (raw.createdAt ?? raw.created_at ?? 0) as number,
 		updatedAt: (raw.updatedAt ?? raw.updated_at ?? 0) as number,
+		structureUpdatedAt: (raw.structureUpdatedAt ?? raw.structure_updated_at ?? 0) as number,
 	};
 }
 
@@ -448,6 +464,7 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 				editingNodeId: newNode.id,
 				noteContents: { ...state.noteContents, [newNode.id]: '' },
 			}));
+			await syncSiblingOrder([newNode.parentId]);
 			await get().openNote(newNode.id, get().focusedPane, false);
 		} catch (error) {
 			console.error('Failed to create sibling:', error);
@@ -477,6 +494,7 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 					noteContents: { ...state.noteContents, [newNode.id]: '' },
 				};
 			});
+			await syncSiblingOrder([newNode.parentId]);
 			await get().openNote(newNode.id, get().focusedPane, true);
 		} catch (error) {
 			console.error('Failed to create child:', error);
@@ -495,6 +513,7 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 				editingNodeId: null,
 				noteContents: { ...state.noteContents, [newNode.id]: '' },
 			}));
+			await syncSiblingOrder([newNode.parentId]);
 			await get().openNote(newNode.id, get().focusedPane, true);
 		} catch (error) {
 			console.error('Failed to quick capture:', error);
@@ -515,6 +534,7 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 				};
 			});
 			void get().loadBacklinks(id);
+			void syncSiblingOrder([get().treeNodes.find((n) => n.id === id)?.parentId ?? null]);
 			if (oldTitle && oldTitle !== newTitle) {
 				const oldLinkedId = await get().resolveWikiLink(oldTitle);
 				if (oldLinkedId && oldLinkedId !== id) void get().loadBacklinks(oldLinkedId);
@@ -570,16 +590,32 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 			set((state) => ({
 				treeNodes: state.treeNodes.map((n) => (ids.includes(n.id) ? { ...n, isPinned: pin } : n)),
 			}));
+			await syncSiblingOrder(get().treeNodes.filter((n) => ids.includes(n.id)).map((n) => n.parentId));
 		} catch (error) {
 			console.error('Failed to batch pin:', error);
 		}
 	},
 
+	batchCopy: async (format, includeChildren) => {
+		const ids = [...get().selectedNodeIds];
+		if (!ids.length) return false;
+		try {
+			get().flushEditorSave();
+			const text = await invoke<string>('batch_export', { ids, format, includeChildren });
+			await navigator.clipboard.writeText(text);
+			return true;
+		} catch (error) {
+			console.error('Failed to batch copy:', error);
+			window.alert('コピーに失敗しました');
+			return false;
+		}
+	},
+
 	moveNote: async (noteId, newParentId, beforeId, afterId) => {
-		set((state) => optimisticMove(state, noteId, newParentId, afterId));
+		set((state) => optimisticMove(state, noteId, newParentId, beforeId, afterId));
 		try {
-			await invoke('move_note', { noteId, newParentId, beforeId, afterId });
-			await get().refreshTree();
+			const keys = await invoke<OrderKeyUpdate[]>('move_note', { noteId, newParentId, beforeId, afterId });
+			set((state) => applyOrderKeys(state, keys));
 		} catch (error) {
 			console.error('Failed to move note:', error);
 			await get().refreshTree();
@@ -606,6 +642,7 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 			set((state) => ({
 				treeNodes: state.treeNodes.map((node) => (node.id === id ? { ...node, isPinned } : node)),
 			}));
+			await syncSiblingOrder([get().treeNodes.find((n) => n.id === id)?.parentId ?? null]);
 		} catch (error) {
 			console.error('Failed to toggle pin:', error);
 		}
@@ -729,6 +766,7 @@ export const useNoteStore = create<NoteStore>((set, get) => ({
 				treeNodes: [...state.treeNodes, newNode],
 				noteContents: { ...state.noteContents, [newNode.id]: '' },
 			}));
+			await syncSiblingOrder([newNode.parentId]);
 			id = newNode.id;
 		}
 		await get().openNote(id, paneId);
@@ -878,17 +916,13 @@ function purgeNotesFromState(state: NoteStore, ids: string[]) {
 function flattenVisible(nodes: TreeNode[], expanded: Set<string>, sortMode: 'manual' | 'recent'): string[] {
 	const result: string[] = [];
 	const traverse = (parentId: string | null) => {
+		// treeNodes keep the backend's display order (pins, sort settings) per parent
 		let children = nodes.filter((n) => n.parentId === parentId);
 		if (parentId === null && sortMode === 'recent') {
 			children = [...children].sort((a, b) => {
 				if (a.isPinned !== b.isPinned) return a.isPinned ? -1 : 1;
 				return b.updatedAt - a.updatedAt;
 			});
-		} else {
-			children.sort((a, b) => {
-				if (a.isPinned !== b.isPinned) return a.isPinned ? -1 : 1;
-				return a.orderKey - b.orderKey;
-			});
 		}
 		for (const child of children) {
 			result.push(child.id);
@@ -899,26 +933,61 @@ function flattenVisible(nodes: TreeNode[], expanded: Set<string>, sortMode: 'man
 	return result;
 }
 
-function optimisticMove(state: NoteStore, noteId: string, newParentId: string | null, afterId?: string) {
+function optimisticMove(state: NoteStore, noteId: string, newParentId: string | null, beforeId?: string, afterId?: string) {
 	const nodes = [...state.treeNodes];
 	const movingNodeIndex = nodes.findIndex((n) => n.id === noteId);
 	if (movingNodeIndex === -1) return state;
 	const [movingNode] = nodes.splice(movingNodeIndex, 1);
-	movingNode.parentId = newParentId;
-	const siblings = nodes.filter((n) => n.parentId === newParentId).sort((a, b) => a.orderKey - b.orderKey);
+	const siblings = nodes.filter((n) => n.parentId === newParentId);
 	let insertIndex = siblings.length;
-	if (afterId) {
-		const targetIndex = siblings.findIndex((n) => n.id === afterId);
+	if (beforeId) {
+		const targetIndex = siblings.findIndex((n) => n.id === beforeId);
 		if (targetIndex !== -1) insertIndex = targetIndex;
+	} else if (afterId) {
+		const targetIndex = siblings.findIndex((n) => n.id === afterId);
+		if (targetIndex !== -1) insertIndex = targetIndex + 1;
 	}
-	siblings.splice(insertIndex, 0, movingNode);
-	siblings.forEach((node, index) => {
-		node.orderKey = index * 1000;
-	});
-	nodes.push(movingNode);
+	// Same midpoint rule as the backend's `key_between`; the real keys and order arrive with the response
+	const prev = siblings[insertIndex - 1]?.orderKey;
+	const next = siblings[insertIndex]?.orderKey;
+	let orderKey = ORDER_STEP;
+	if (prev !== undefined && next !== undefined) orderKey = (prev + next) / 2;
+	else if (prev !== undefined) orderKey = prev + ORDER_STEP;
+	else if (next !== undefined) orderKey = next - ORDER_STEP;
+	const anchor = siblings[insertIndex] ?? siblings[siblings.length - 1];
+	const at = anchor ? nodes.indexOf(anchor) + (siblings[insertIndex] ? 0 : 1) : nodes.length;
+	nodes.splice(at, 0, { ...movingNode, parentId: newParentId, orderKey });
 	return { treeNodes: refreshHasChildrenFlags(nodes) };
 }
 
+/** Apply the backend's keys for one parent's children, re-placing them in the display order they arrive in. */
+function applyOrderKeys(state: NoteStore, keys: OrderKeyUpdate[]) {
+	const byId = new Map(keys.map((k) => [k.id, k.orderKey]));
+	const first = state.treeNodes.findIndex((n) => byId.has(n.id));
+	if (first === -1) return {};
+	const current = new Map(state.treeNodes.filter((n) => byId.has(n.id)).map((n) => [n.id, n]));
+	const ordered = keys.flatMap((k) => {
+		const node = current.get(k.id);
+		return node ? [{ ...node, orderKey: k.orderKey }] : [];
+	});
+	const treeNodes = state.treeNodes.filter((n) => !byId.has(n.id));
+	const at = state.treeNodes.slice(0, first).filter((n) => !byId.has(n.id)).length;
+	treeNodes.splice(at, 0, ...ordered);
+	return { treeNodes };
+}
+
+/** Re-place each parent's children in the backend's display order after a create, rename or pin. */
+async function syncSiblingOrder(parentIds: (string | null)[]) {
+	for (const parentId of new Set(parentIds)) {
+		try {
+			const keys = await invoke<OrderKeyUpdate[]>('get_sibling_order', { parentId });
+			useNoteStore.setState((state) => applyOrderKeys(state, keys));
+		} catch (error) {
+			console.error('Failed to load sibling order:', error);
+		}
+	}
+}
+
 function refreshHasChildrenFlags(nodes: TreeNode[]): TreeNode[] {
 	return nodes.map((n) => ({
 		...n,
@@ -0,0 +1,514 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::AppState;
+use crate::commands::export::{load_note_rows, NoteRow};
+use crate::commands::folder::{
+    children_by_parent, insert_import, layout_level, note_file, sanitize_file_name, split_note_file,
+    ImportEntry, ImportProgress,
+};
+use crate::commands::tree::parse_root_id;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const ARCHIVE_FORMAT: &str = "ultra-memo-archive";
+/// Bump when the layout changes; older archives must stay importable.
+pub const ARCHIVE_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const ASSETS_DIR: &str = "assets";
+
+/// `manifest.json` at the archive root. Note files follow the `export_markdown_folder` layout;
+/// the manifest is authoritative for the tree and metadata.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    /// Notes in tree order.
+    pub notes: Vec<ArchiveNote>,
+    #[serde(default)]
+    pub assets: Vec<ArchiveAsset>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveNote {
+    pub id: i64,
+    /// None, or a note outside the archive, for top-level notes.
+    pub parent_id: Option<i64>,
+    /// Path of the note's Markdown file inside the archive.
+    pub path: String,
+    pub title: String,
+    pub order_key: f64,
+    pub is_pinned: bool,
+    pub is_markdown_view: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A local file referenced from note content, copied into the archive.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveAsset {
+    /// Path inside the archive.
+    pub path: String,
+    /// The link target as written in the notes; rewritten on import.
+    pub source: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveExportSummary {
+    pub path: String,
+    pub notes: usize,
+    pub assets: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveImportSummary {
+    pub notes: usize,
+    pub assets: usize,
+    /// Top-level notes created under the target parent.
+    pub root_ids: Vec<String>,
+}
+
+/// Link and image targets in Markdown (`[..](target)`) and inline HTML (`src="target"`).
+/// The target is capture group 1.
+fn link_target_regexes() -> [Regex; 2] {
+    [
+        Regex::new(r"\]\(\s*<?([^)>\s]+)>?").unwrap(),
+        Regex::new(r#"src\s*=\s*"([^"]+)""#).unwrap(),
+    ]
+}
+
+fn link_targets(content: &str) -> Vec<String> {
+    link_target_regexes()
+        .iter()
+        .flat_map(|re| re.captures_iter(content).map(|caps| caps[1].to_string()).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Replace link targets that equal a key of `rewrites` as a whole; other text is left alone.
+fn rewrite_link_targets(content: &str, rewrites: &HashMap<String, String>) -> String {
+    let mut content = content.to_string();
+    for re in link_target_regexes() {
+        content = re
+            .replace_all(&content, |caps: &regex::Captures| {
+                let whole = caps.get(0).unwrap();
+                let target = caps.get(1).unwrap();
+                match rewrites.get(target.as_str()) {
+                    Some(dest) => {
+                        let start = target.start() - whole.start();
+                        let end = target.end() - whole.start();
+                        format!("{}{}{}", &whole.as_str()[..start], dest, &whole.as_str()[end..])
+                    }
+                    None => whole.as_str().to_string(),
+                }
+            })
+            .into_owned();
+    }
+    content
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `file://` URL for an absolute path, percent-encoded so it stays one Markdown link target.
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+/// The local file a link target points at, if it exists. Only absolute paths and `file://`
+/// URLs qualify; notes have no base directory for relative ones.
+fn local_file(target: &str) -> Option<PathBuf> {
+    let path = match target.strip_prefix("file://") {
+        Some(url) => {
+            let path = percent_decode(url);
+            // `file:///C:/…` on Windows
+            match path.strip_prefix('/') {
+                Some(rest) if rest.get(1..2) == Some(":") => rest.to_string(),
+                _ => path,
+            }
+        }
+        None => target.to_string(),
+    };
+    let path = PathBuf::from(path);
+    (path.is_absolute() && path.is_file()).then_some(path)
+}
+
+fn write_entry(zip: &mut ZipWriter<File>, name: &str, data: &[u8]) -> Result<(), String> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(name, options).map_err(|e| e.to_string())?;
+    zip.write_all(data).map_err(|e| e.to_string())
+}
+
+/// Write `start`'s descendants, their manifest and linked local files to the zip at `path`.
+fn write_archive(path: String, notes: &[NoteRow], start: Option<i64>, exported_at: i64) -> Result<ArchiveExportSummary, String> {
+    let mut files = Vec::new();
+    layout_level(&children_by_parent(notes), start, "", &mut files);
+
+    let mut assets: Vec<(ArchiveAsset, PathBuf)> = Vec::new();
+    let mut seen = HashSet::new();
+    for (_, note) in &files {
+        for target in link_targets(&note.content) {
+            if seen.contains(&target) {
+                continue;
+            }
+            if let Some(file) = local_file(&target) {
+                let name = sanitize_file_name(&file.file_name().unwrap_or_default().to_string_lossy());
+                let asset = ArchiveAsset {
+                    path: format!("{}/{:03}-{}", ASSETS_DIR, assets.len() + 1, name),
+                    source: target.clone(),
+                };
+                assets.push((asset, file));
+            }
+            seen.insert(target);
+        }
+    }
+
+    let manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at,
+        notes: files
+            .iter()
+            .map(|(file, note)| ArchiveNote {
+                id: note.id,
+                parent_id: note.parent_id,
+                path: file.clone(),
+                title: note.title.clone(),
+                order_key: note.order_key,
+                is_pinned: note.is_pinned,
+                is_markdown_view: note.is_markdown_view,
+                created_at: note.created_at,
+                updated_at: note.updated_at,
+            })
+            .collect(),
+        assets: assets.iter().map(|(asset, _)| asset.clone()).collect(),
+    };
+
+    let mut zip = ZipWriter::new(File::create(&path).map_err(|e| e.to_string())?);
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    write_entry(&mut zip, MANIFEST_NAME, &json)?;
+    for (file, note) in &files {
+        write_entry(&mut zip, file, note_file(note).as_bytes())?;
+    }
+    for (asset, file) in &assets {
+        let data = fs::read(file).map_err(|e| e.to_string())?;
+        write_entry(&mut zip, &asset.path, &data)?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(ArchiveExportSummary {
+        path,
+        notes: files.len(),
+        assets: assets.len(),
+    })
+}
+
+/// Export the tree (or the subtree under `root_id`) to a single `.zip`: the Markdown folder
+/// layout of `export_markdown_folder`, a `manifest.json` with ids, order and metadata,
+/// and the local files the notes link to under `assets/`.
+#[tauri::command]
+pub async fn export_zip_archive(
+    app: AppHandle,
+    path: String,
+    root_id: Option<String>,
+) -> Result<ArchiveExportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root_id_int = parse_root_id(root_id)?;
+        let (notes, start) = {
+            let state = app.state::<AppState>();
+            let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+            load_note_rows(&conn, root_id_int)?
+        };
+        let exported_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        write_archive(path, &notes, start, exported_at)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut file = zip.by_name(name).map_err(|_| format!("Missing {} in archive", name))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
+/// Reject manifests whose notes can't form a tree: repeated ids or parent cycles.
+fn validate_manifest(notes: &[ArchiveNote]) -> Result<(), String> {
+    let mut parents: HashMap<i64, Option<i64>> = HashMap::new();
+    for note in notes {
+        if parents.insert(note.id, note.parent_id).is_some() {
+            return Err(format!("Invalid manifest: note {} appears twice", note.id));
+        }
+    }
+    for note in notes {
+        let mut current = note.parent_id;
+        let mut steps = 0;
+        while let Some(id) = current {
+            if id == note.id || steps > notes.len() {
+                return Err(format!("Invalid manifest: note {} is its own ancestor", note.id));
+            }
+            current = parents.get(&id).copied().flatten();
+            steps += 1;
+        }
+    }
+    Ok(())
+}
+
+fn build_entries(
+    notes: &[ArchiveNote],
+    contents: &mut [Option<String>],
+    by_parent: &HashMap<Option<i64>, Vec<usize>>,
+    parent_id: Option<i64>,
+) -> Vec<ImportEntry> {
+    let Some(indices) = by_parent.get(&parent_id) else {
+        return Vec::new();
+    };
+    indices
+        .iter()
+        .map(|&i| {
+            let note = &notes[i];
+            ImportEntry {
+                title: note.title.clone(),
+                content: contents[i].take().unwrap_or_default(),
+                created_at: note.created_at,
+                updated_at: note.updated_at,
+                is_pinned: note.is_pinned,
+                is_markdown_view: note.is_markdown_view,
+                children: build_entries(notes, contents, by_parent, Some(note.id)),
+            }
+        })
+        .collect()
+}
+
+/// Read the archive at `path` into import entries, extracting its assets to `assets_dir`
+/// (created only when there are assets) and pointing links at the extracted files.
+/// Returns the entries and the number of assets.
+fn read_archive(path: &str, assets_dir: &Path) -> Result<(Vec<ImportEntry>, usize), String> {
+    let mut zip = ZipArchive::new(File::open(path).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    let manifest: ArchiveManifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_NAME)?)
+        .map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err("Not an Ultra Memo archive".into());
+    }
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!("Archive version {} is newer than this app supports", manifest.version));
+    }
+    validate_manifest(&manifest.notes)?;
+
+    let mut contents = Vec::with_capacity(manifest.notes.len());
+    for note in &manifest.notes {
+        let raw = read_entry(&mut zip, &note.path)?;
+        let raw = String::from_utf8_lossy(&raw);
+        contents.push(Some(split_note_file(&raw).1));
+    }
+
+    // File names come from the archive path only
+    let mut rewrites: HashMap<String, String> = HashMap::new();
+    if !manifest.assets.is_empty() {
+        fs::create_dir_all(assets_dir).map_err(|e| e.to_string())?;
+        for asset in &manifest.assets {
+            let Some(name) = Path::new(&asset.path).file_name() else {
+                continue;
+            };
+            let dest = assets_dir.join(name);
+            fs::write(&dest, read_entry(&mut zip, &asset.path)?).map_err(|e| e.to_string())?;
+            rewrites.insert(asset.source.clone(), file_url(&dest));
+        }
+        for content in contents.iter_mut().flatten() {
+            *content = rewrite_link_targets(content, &rewrites);
+        }
+    }
+
+    // Notes whose parent isn't in the archive become top-level
+    let ids: HashSet<i64> = manifest.notes.iter().map(|n| n.id).collect();
+    let mut by_parent: HashMap<Option<i64>, Vec<usize>> = HashMap::new();
+    for (i, note) in manifest.notes.iter().enumerate() {
+        let parent = note.parent_id.filter(|p| ids.contains(p));
+        by_parent.entry(parent).or_default().push(i);
+    }
+    Ok((build_entries(&manifest.notes, &mut contents, &by_parent, None), rewrites.len()))
+}
+
+/// Read the archive into `assets_dir` and insert its notes under `parent_id` in one transaction.
+fn import_archive(
+    app: &AppHandle,
+    path: &str,
+    parent_id: Option<i64>,
+    assets_dir: &Path,
+    now: i64,
+) -> Result<ArchiveImportSummary, String> {
+    let (entries, assets) = read_archive(path, assets_dir)?;
+
+    let state = app.state::<AppState>();
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let total: usize = entries.iter().map(ImportEntry::count).sum();
+    let mut processed = 0;
+    let mut progress = || {
+        processed += 1;
+        if processed % 100 == 0 || processed == total {
+            let _ = app.emit("archive-import-progress", ImportProgress { processed, total });
+        }
+    };
+    let root_ids = insert_import(&tx, &entries, parent_id, now, &mut progress)?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ArchiveImportSummary {
+        notes: total,
+        assets,
+        root_ids: root_ids.iter().map(|id| id.to_string()).collect(),
+    })
+}
+
+/// Import an archive written by `export_zip_archive` under `target_parent_id`, appended after
+/// its existing children. Assets are extracted to the app data dir and links to them rewritten
+/// to `file://` URLs. Emits `archive-import-progress` while inserting; the whole import is one
+/// transaction, and the extracted assets are removed again if it fails.
+#[tauri::command]
+pub async fn import_zip_archive(
+    app: AppHandle,
+    path: String,
+    target_parent_id: Option<String>,
+) -> Result<ArchiveImportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let parent_id_int = parse_root_id(target_parent_id).map_err(|_| "Invalid Parent ID".to_string())?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        let assets_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?
+            .join(ASSETS_DIR)
+            .join(now.to_string());
+        let summary = import_archive(&app, &path, parent_id_int, &assets_dir, now);
+        if summary.is_err() {
+            // Nothing links to the extracted assets unless the notes were committed
+            let _ = fs::remove_dir_all(&assets_dir);
+        }
+        summary
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_test_note, open_in_memory, test_outline};
+
+    fn archive_note(id: i64, parent_id: Option<i64>) -> ArchiveNote {
+        ArchiveNote {
+            id,
+            parent_id,
+            path: format!("{}.md", id),
+            title: id.to_string(),
+            order_key: 1.0,
+            is_pinned: false,
+            is_markdown_view: false,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn zip_archive_round_trips() {
+        let dir = std::env::temp_dir().join(format!("ultra-memo-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let picture = dir.join("my pic.png");
+        fs::write(&picture, b"png bytes").unwrap();
+        let picture = picture.to_string_lossy().to_string();
+        let source_url = file_url(Path::new(&picture));
+
+        // The second link only shares a prefix with the asset and must stay as written
+        let content = format!(
+            "![pic]({url}) and [other]({url}.bak)\n<img src=\"{url}\">\nPlain mention: {url}\n",
+            url = source_url
+        );
+        let source = open_in_memory().unwrap();
+        let root = insert_test_note(&source, None, "Root", &content, 1024.0, false);
+        insert_test_note(&source, Some(root), "Pinned child", "child", 2048.0, true);
+        insert_test_note(&source, Some(root), "First child", "---\ntags: [\"a b\"]\n---\nbody", 1024.0, false);
+        insert_test_note(&source, None, "Second root", "", 2048.0, false);
+
+        let zip_path = dir.join("notes.zip").to_string_lossy().to_string();
+        let (notes, start) = load_note_rows(&source, None).unwrap();
+        let exported = write_archive(zip_path.clone(), &notes, start, 0).unwrap();
+        assert_eq!((exported.notes, exported.assets), (4, 1));
+
+        let assets_dir = dir.join("assets");
+        let (entries, assets) = read_archive(&zip_path, &assets_dir).unwrap();
+        assert_eq!(assets, 1);
+        let extracted = assets_dir.join("001-my pic.png");
+        assert_eq!(fs::read(&extracted).unwrap(), b"png bytes");
+
+        let mut target = open_in_memory().unwrap();
+        let tx = target.transaction().unwrap();
+        insert_import(&tx, &entries, None, 0, &mut || {}).unwrap();
+        tx.commit().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let extracted_url = file_url(&extracted);
+        assert!(extracted_url.contains("my%20pic.png"));
+        let mut expected = test_outline(&source);
+        expected[0].2 = format!(
+            "![pic]({new}) and [other]({old}.bak)\n<img src=\"{new}\">\nPlain mention: {old}\n",
+            new = extracted_url,
+            old = source_url
+        );
+        assert_eq!(test_outline(&target), expected);
+    }
+
+    #[test]
+    fn manifest_rejects_duplicate_ids_and_cycles() {
+        assert!(validate_manifest(&[archive_note(1, None), archive_note(2, Some(1))]).is_ok());
+        assert!(validate_manifest(&[archive_note(1, None), archive_note(1, None)]).is_err());
+        assert!(validate_manifest(&[archive_note(1, Some(2)), archive_note(2, Some(1))]).is_err());
+        assert!(validate_manifest(&[archive_note(1, Some(1))]).is_err());
+    }
+}
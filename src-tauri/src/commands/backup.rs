@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::snapshots::{self, SnapshotFile};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// Snapshot file name; pass to `restore_backup`.
+    pub id: String,
+    /// "auto", "manual" or "pre-restore".
+    pub kind: String,
+    pub created_at: i64,
+    pub size: u64,
+}
+
+impl From<SnapshotFile> for BackupInfo {
+    fn from(s: SnapshotFile) -> Self {
+        BackupInfo {
+            id: s.file_name,
+            kind: s.kind,
+            created_at: s.created_at,
+            size: s.size,
+        }
+    }
+}
+
+/// Back up the live database with SQLite's online backup API, to `dest`
+/// or, when omitted, as a manual snapshot in the app data dir.
+#[tauri::command]
+pub async fn backup_database(app: AppHandle, dest: Option<String>) -> Result<Option<BackupInfo>, String> {
+    tauri::async_runtime::spawn_blocking(move || match dest {
+        Some(path) => {
+            snapshots::backup_to(&app, &PathBuf::from(path))?;
+            Ok(None)
+        }
+        None => Ok(Some(snapshots::take_snapshot(&app, "manual")?.into())),
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Snapshots in the app data dir, newest first.
+#[tauri::command]
+pub fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    Ok(snapshots::list_snapshots(&app)?.into_iter().map(BackupInfo::from).collect())
+}
+
+/// Schedule snapshot `id` to replace the database on the next start.
+/// The snapshot is integrity-checked first, so this runs off the main thread.
+#[tauri::command]
+pub async fn restore_backup(app: AppHandle, id: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || snapshots::request_restore(&app, &id))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -1,7 +1,36 @@
-use tauri::State;
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
 use crate::AppState;
+use crate::commands::export::{load_note_rows, push_note_markdown, walk_tree, NoteRow};
+use crate::commands::move_note::{move_notes, sibling_keys, OrderKeyUpdate};
 use rusqlite::params;
 
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchExportFormat {
+    /// Notes as headings, nested by depth, frontmatter dropped.
+    Markdown,
+    /// Indented titles and content, frontmatter dropped.
+    Text,
+    /// A JSON array of notes.
+    Json,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchExportNote {
+    pub id: String,
+    /// Parent within the export; None for top-level notes of the selection.
+    pub parent_id: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub depth: usize,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
 #[tauri::command]
 pub fn batch_soft_delete(state: State<'_, AppState>, ids: Vec<String>) -> Result<(), String> {
     let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
@@ -49,3 +78,128 @@ pub fn batch_toggle_pin(state: State<'_, AppState>, ids: Vec<String>, pin: bool)
     }
     Ok(())
 }
+
+/// Move several notes as one block, keeping their relative tree order.
+/// Same before/after semantics and return value as `move_note`.
+#[tauri::command]
+pub fn batch_move(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    new_parent_id: Option<String>,
+    before_id: Option<String>,
+    after_id: Option<String>,
+) -> Result<Vec<OrderKeyUpdate>, String> {
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let mut id_ints = Vec::new();
+    for id_str in ids {
+        id_ints.push(id_str.parse::<i64>().map_err(|_| format!("Invalid ID: {}", id_str))?);
+    }
+    let parse_opt = |s: Option<String>| -> Result<Option<i64>, String> {
+        match s {
+            Some(s) => s.parse::<i64>().map(Some).map_err(|_| format!("Invalid ID: {}", s)),
+            None => Ok(None),
+        }
+    };
+    let new_parent_id_int = parse_opt(new_parent_id)?;
+    let before_id_int = parse_opt(before_id)?;
+    let after_id_int = parse_opt(after_id)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    move_notes(&tx, &id_ints, new_parent_id_int, before_id_int, after_id_int, now)?;
+    let keys = sibling_keys(&tx, new_parent_id_int)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(keys)
+}
+
+/// Export a selection of notes, with their subtrees when `include_children`, in the tree's
+/// display order (pinned first, then each parent's sort setting).
+/// Selected notes nested under other exported notes are nested in the output too.
+#[tauri::command]
+pub async fn batch_export(
+    app: AppHandle,
+    ids: Vec<String>,
+    format: BatchExportFormat,
+    include_children: Option<bool>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut selected = HashSet::new();
+        for id_str in ids {
+            selected.insert(id_str.parse::<i64>().map_err(|_| format!("Invalid ID: {}", id_str))?);
+        }
+        let notes = {
+            let state = app.state::<AppState>();
+            let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+            load_note_rows(&conn, None)?.0
+        };
+        render_batch_export(&notes, &selected, format, include_children.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn render_batch_export(
+    notes: &[NoteRow],
+    selected: &HashSet<i64>,
+    format: BatchExportFormat,
+    include_children: bool,
+) -> Result<String, String> {
+    // Stack of (tree depth, note) for the exported ancestors of the current note
+    let mut ancestors: Vec<(usize, &NoteRow)> = Vec::new();
+    let mut exported: Vec<(&NoteRow, Option<i64>, usize)> = Vec::new();
+    walk_tree(notes, None, 0, &mut |note, depth| {
+        while ancestors.last().is_some_and(|(d, _)| *d >= depth) {
+            ancestors.pop();
+        }
+        if selected.contains(&note.id) || (include_children && !ancestors.is_empty()) {
+            exported.push((note, ancestors.last().map(|(_, n)| n.id), ancestors.len()));
+            ancestors.push((depth, note));
+        }
+        true
+    });
+
+    let mut output = String::new();
+    match format {
+        BatchExportFormat::Markdown => {
+            for (note, _, depth) in &exported {
+                let (_, body) = crate::frontmatter::parse(&note.content);
+                push_note_markdown(&mut output, &note.title, body.trim_start_matches(['\r', '\n']), *depth);
+            }
+        }
+        BatchExportFormat::Text => {
+            for (note, _, depth) in &exported {
+                let indent = "  ".repeat(*depth);
+                output.push_str(&format!("{}{}\n", indent, note.title));
+                let (_, body) = crate::frontmatter::parse(&note.content);
+                for line in body.trim().lines() {
+                    if line.trim().is_empty() {
+                        output.push('\n');
+                    } else {
+                        output.push_str(&format!("{}  {}\n", indent, line));
+                    }
+                }
+                output.push('\n');
+            }
+        }
+        BatchExportFormat::Json => {
+            let items: Vec<BatchExportNote> = exported
+                .iter()
+                .map(|(note, parent_id, depth)| BatchExportNote {
+                    id: note.id.to_string(),
+                    parent_id: parent_id.map(|id| id.to_string()),
+                    title: note.title.clone(),
+                    content: note.content.clone(),
+                    depth: *depth,
+                    created_at: note.created_at,
+                    updated_at: note.updated_at,
+                })
+                .collect();
+            output = serde_json::to_string_pretty(&items).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(output.trim_end().to_string())
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use tauri::State;
+use crate::AppState;
+use crate::commands::links::rewrite_wiki_links;
+use crate::commands::tree::{tree_node_from_row, TreeNode, TREE_NODE_COLUMNS};
+use crate::ordering;
+use rusqlite::params;
+
+const DEFAULT_TITLE_SUFFIX: &str = " (コピー)";
+
+struct CopyRow {
+    id: i64,
+    parent_id: Option<i64>,
+    title: String,
+    content: String,
+    order_key: f64,
+    is_pinned: i64,
+    is_markdown_view: i64,
+}
+
+/// Deep-copy a note (optionally with its live subtree) in one transaction.
+/// - target_parent_id: where the copy goes; defaults to the original's parent,
+///   in which case the copy is placed right after the original.
+/// - title_suffix: appended to the copied root's title (default " (コピー)").
+/// - rewrite_links: also suffix every copied title and rewrite `[[links]]` between
+///   copied notes so they point at the copies instead of the originals.
+#[tauri::command]
+pub fn duplicate_note(
+    state: State<'_, AppState>,
+    id: String,
+    include_children: bool,
+    target_parent_id: Option<String>,
+    title_suffix: Option<String>,
+    rewrite_links: bool,
+) -> Result<TreeNode, String> {
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let id_int = id.parse::<i64>().map_err(|_| "Invalid ID format")?;
+    let target_parent_int = match target_parent_id.as_ref() {
+        Some(id_str) => Some(id_str.parse::<i64>().map_err(|_| "Invalid Parent ID")?),
+        None => None,
+    };
+    let suffix = title_suffix.unwrap_or_else(|| DEFAULT_TITLE_SUFFIX.to_string());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    if let Some(parent) = target_parent_int {
+        tx.query_row("SELECT 1 FROM notes WHERE id = ? AND is_deleted = 0", [parent], |_| Ok(()))
+            .map_err(|_| "Target parent not found")?;
+    }
+
+    // Parents come before children (ordered by depth)
+    let rows: Vec<CopyRow> = {
+        let mut stmt = tx
+            .prepare(
+                "WITH RECURSIVE sub(id, depth) AS (
+                    SELECT id, 0 FROM notes WHERE id = ?1 AND is_deleted = 0
+                    UNION ALL
+                    SELECT c.id, sub.depth + 1 FROM notes c
+                    JOIN sub ON c.parent_id = sub.id
+                    WHERE c.is_deleted = 0 AND ?2
+                 )
+                 SELECT n.id, n.parent_id, n.title, n.content, n.order_key, n.is_pinned, n.is_markdown_view
+                 FROM sub JOIN notes n ON n.id = sub.id
+                 ORDER BY sub.depth, n.order_key",
+            )
+            .map_err(|e| e.to_string())?;
+        let mapped = stmt
+            .query_map(params![id_int, include_children], |row| {
+                Ok(CopyRow {
+                    id: row.get(0)?,
+                    parent_id: row.get(1)?,
+                    title: row.get(2)?,
+                    content: row.get(3)?,
+                    order_key: row.get(4)?,
+                    is_pinned: row.get(5)?,
+                    is_markdown_view: row.get(6)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut rows = Vec::new();
+        for row in mapped {
+            rows.push(row.map_err(|e| e.to_string())?);
+        }
+        rows
+    };
+    let root = rows.first().ok_or("Note not found")?;
+
+    // Right after the original when staying under the same parent, otherwise appended
+    let dest_parent = if target_parent_id.is_some() { target_parent_int } else { root.parent_id };
+    let siblings = ordering::child_ids(&tx, dest_parent, &[]).map_err(|e| e.to_string())?;
+    let (prev_id, next_id) = match siblings.iter().position(|&sid| sid == root.id) {
+        Some(pos) => (Some(root.id), siblings.get(pos + 1).copied()),
+        None => (siblings.last().copied(), None),
+    };
+    let root_order = ordering::keys_between(&tx, dest_parent, prev_id, next_id, 1)
+        .map_err(|e| e.to_string())?[0];
+
+    let mut renames: HashMap<String, String> = HashMap::new();
+    if rewrite_links {
+        for row in &rows {
+            renames.insert(row.title.trim().to_lowercase(), format!("{}{}", row.title, suffix));
+        }
+    }
+
+    let mut new_ids: HashMap<i64, i64> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        let (parent, order_key) = if i == 0 {
+            (dest_parent, root_order)
+        } else {
+            (row.parent_id.and_then(|p| new_ids.get(&p).copied()), row.order_key)
+        };
+        let title = if i == 0 || rewrite_links {
+            format!("{}{}", row.title, suffix)
+        } else {
+            row.title.clone()
+        };
+        let content = rewrite_wiki_links(&row.content, &renames);
+
+        tx.execute(
+            "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, is_pinned, is_markdown_view, created_at, updated_at, structure_updated_at)
+             VALUES (?, ?, ?, ?, 0, 0, ?, ?, ?, ?, ?)",
+            params![parent, title, content, order_key, row.is_pinned, row.is_markdown_view, now, now, now],
+        )
+        .map_err(|e| e.to_string())?;
+        new_ids.insert(row.id, tx.last_insert_rowid());
+    }
+
+    let new_root_id = new_ids[&id_int];
+    let node = tx
+        .query_row(
+            &format!("SELECT {} FROM notes n WHERE n.id = ?", TREE_NODE_COLUMNS),
+            [new_root_id],
+            tree_node_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(node)
+}
@@ -0,0 +1,839 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use quick_xml::escape::resolve_html5_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::AppState;
+use crate::commands::folder::{insert_import, ImportEntry, ImportProgress};
+use crate::commands::tree::parse_root_id;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnexAttachment {
+    pub note_title: String,
+    pub file_name: Option<String>,
+    pub mime: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnexImportSummary {
+    /// The notebook note holding the imported notes.
+    pub notebook_id: String,
+    pub notes: usize,
+    /// Attachments that were not imported; their place in the text is marked instead.
+    pub attachments: Vec<EnexAttachment>,
+    /// Notes whose ENML could not be converted and were kept as raw HTML.
+    pub unconverted: Vec<String>,
+}
+
+/// `20231231T235959Z` (ENEX's UTC timestamp format) to epoch millis.
+fn parse_enex_time(s: &str) -> Option<i64> {
+    let s = s.trim();
+    if s.len() != 16 || s.as_bytes()[8] != b'T' || !s.ends_with('Z') {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (num(0..4)?, num(4..6)?, num(6..8)?);
+    let (hour, minute, second) = (num(9..11)?, num(11..13)?, num(13..15)?);
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days = era * 146097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719468;
+    Some((((days * 24 + hour) * 60 + minute) * 60 + second) * 1000)
+}
+
+fn attr_value(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+enum ListKind {
+    Bullet,
+    Ordered(usize),
+    /// Evernote's newer checklists: `<ul style="--en-todo:true">`.
+    Todo,
+}
+
+impl ListKind {
+    fn marker_width(&self) -> usize {
+        match self {
+            ListKind::Ordered(_) => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// Where text is currently written. Links, quotes and table cells are rendered once complete.
+enum Capture {
+    Document,
+    Link(String),
+    Quote,
+    Cell,
+}
+
+/// What to do when an element closes.
+enum Close {
+    Nothing,
+    Block,
+    Paragraph,
+    CodeLine,
+    Code,
+    Wrap(&'static str),
+    List,
+    Item,
+    Table,
+    Row,
+    /// Pops the link, quote or cell frame opened by the element.
+    Frame,
+    Skip,
+}
+
+/// Streaming ENML (Evernote's XHTML subset) to Markdown converter.
+struct EnmlConverter {
+    frames: Vec<(Capture, String)>,
+    closers: Vec<Close>,
+    lists: Vec<ListKind>,
+    tables: Vec<Vec<Vec<String>>>,
+    /// A list marker was just written and the item has no text yet.
+    item_start: bool,
+    code: usize,
+    skip: usize,
+    /// Attachment file names by `en-media` hash.
+    media: HashMap<String, String>,
+}
+
+impl EnmlConverter {
+    fn new(media: HashMap<String, String>) -> Self {
+        EnmlConverter {
+            frames: vec![(Capture::Document, String::new())],
+            closers: Vec::new(),
+            lists: Vec::new(),
+            tables: Vec::new(),
+            item_start: false,
+            code: 0,
+            skip: 0,
+            media,
+        }
+    }
+
+    fn out(&mut self) -> &mut String {
+        &mut self.frames.last_mut().expect("document frame").1
+    }
+
+    fn at_line_start(&self) -> bool {
+        let buf = &self.frames.last().expect("document frame").1;
+        buf.is_empty() || buf.ends_with('\n')
+    }
+
+    /// Indentation that continues the innermost list item, or nests inside it with `inner = false`.
+    fn indent(&self, inner: bool) -> String {
+        let levels = if inner { self.lists.len() } else { self.lists.len().saturating_sub(1) };
+        " ".repeat(self.lists[..levels].iter().map(ListKind::marker_width).sum())
+    }
+
+    fn in_flow(&self) -> bool {
+        matches!(self.frames.last(), Some((Capture::Document | Capture::Quote, _)))
+    }
+
+    fn line_break(&mut self) {
+        if self.item_start {
+            return;
+        }
+        if !self.at_line_start() {
+            self.out().push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if self.item_start {
+            return;
+        }
+        self.line_break();
+        if !self.lists.is_empty() {
+            return;
+        }
+        let out = self.out();
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    }
+
+    /// Write inline markup or text, indenting it when it starts a line inside a list.
+    fn inline(&mut self, s: &str) {
+        if self.at_line_start() && !self.item_start && !self.lists.is_empty() && self.in_flow() {
+            let indent = self.indent(true);
+            self.out().push_str(&indent);
+        }
+        self.item_start = false;
+        self.out().push_str(s);
+    }
+
+    fn text(&mut self, s: &str) {
+        if self.skip > 0 {
+            return;
+        }
+        if self.code > 0 {
+            self.out().push_str(s);
+            return;
+        }
+        let mut collapsed = String::with_capacity(s.len());
+        for c in s.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                if !collapsed.ends_with(' ') {
+                    collapsed.push(' ');
+                }
+            } else {
+                collapsed.push(c);
+            }
+        }
+        let collapsed = if self.at_line_start() || self.out().ends_with(' ') || self.item_start {
+            collapsed.trim_start()
+        } else {
+            collapsed.as_str()
+        };
+        if !collapsed.is_empty() {
+            self.inline(collapsed);
+        }
+    }
+
+    fn start_item(&mut self, checked: Option<bool>) {
+        if !self.at_line_start() {
+            self.out().push('\n');
+        }
+        let marker = match self.lists.last_mut() {
+            None | Some(ListKind::Bullet) => "- ".to_string(),
+            Some(ListKind::Todo) => format!("- [{}] ", if checked == Some(true) { "x" } else { " " }),
+            Some(ListKind::Ordered(n)) => {
+                *n += 1;
+                format!("{}. ", *n - 1)
+            }
+        };
+        let indent = self.indent(false);
+        self.out().push_str(&indent);
+        self.out().push_str(&marker);
+        self.item_start = true;
+    }
+
+    fn start(&mut self, e: &BytesStart) {
+        let name = e.local_name().as_ref().to_ascii_lowercase();
+        let style = attr_value(e, b"style").unwrap_or_default().replace(' ', "");
+        let close = match name.as_slice() {
+            _ if self.skip > 0 => Close::Skip,
+            b"en-crypt" => {
+                self.inline("[暗号化されたテキスト]");
+                Close::Skip
+            }
+            b"head" | b"style" | b"script" | b"title" => Close::Skip,
+            b"div" | b"section" | b"article" | b"header" | b"footer" | b"center" if self.code > 0 => Close::CodeLine,
+            b"div" if style.contains("-en-codeblock:true") => {
+                self.blank_line();
+                self.inline("```\n");
+                self.code += 1;
+                Close::Code
+            }
+            b"div" | b"section" | b"article" | b"header" | b"footer" | b"center" => {
+                self.line_break();
+                Close::Block
+            }
+            b"p" => {
+                self.blank_line();
+                Close::Paragraph
+            }
+            b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" => {
+                self.blank_line();
+                let level = (name[1] - b'0') as usize;
+                self.inline(&format!("{} ", "#".repeat(level)));
+                Close::Paragraph
+            }
+            b"pre" => {
+                self.blank_line();
+                self.inline("```\n");
+                self.code += 1;
+                Close::Code
+            }
+            _ if self.code > 0 => Close::Nothing,
+            b"b" | b"strong" => {
+                self.inline("**");
+                Close::Wrap("**")
+            }
+            b"i" | b"em" => {
+                self.inline("*");
+                Close::Wrap("*")
+            }
+            b"s" | b"strike" | b"del" => {
+                self.inline("~~");
+                Close::Wrap("~~")
+            }
+            b"code" => {
+                self.inline("`");
+                Close::Wrap("`")
+            }
+            b"a" => {
+                self.frames.push((Capture::Link(attr_value(e, b"href").unwrap_or_default()), String::new()));
+                Close::Frame
+            }
+            b"blockquote" => {
+                self.blank_line();
+                self.frames.push((Capture::Quote, String::new()));
+                Close::Frame
+            }
+            b"ul" | b"ol" => {
+                if self.lists.is_empty() {
+                    self.blank_line();
+                } else if !self.at_line_start() {
+                    self.out().push('\n');
+                }
+                self.item_start = false;
+                self.lists.push(if name == b"ol" {
+                    ListKind::Ordered(attr_value(e, b"start").and_then(|s| s.parse().ok()).unwrap_or(1))
+                } else if style.contains("--en-todo:true") {
+                    ListKind::Todo
+                } else {
+                    ListKind::Bullet
+                });
+                Close::List
+            }
+            b"li" => {
+                self.start_item(Some(style.contains("--en-checked:true")));
+                Close::Item
+            }
+            b"table" => {
+                self.blank_line();
+                self.tables.push(Vec::new());
+                Close::Table
+            }
+            b"tr" => {
+                if let Some(table) = self.tables.last_mut() {
+                    table.push(Vec::new());
+                }
+                Close::Row
+            }
+            b"td" | b"th" => {
+                self.frames.push((Capture::Cell, String::new()));
+                Close::Frame
+            }
+            _ => {
+                self.empty(e);
+                Close::Nothing
+            }
+        };
+        if matches!(close, Close::Skip) {
+            self.skip += 1;
+        }
+        self.closers.push(close);
+    }
+
+    /// Self-closing elements, and the parts of other elements handled at their start tag.
+    fn empty(&mut self, e: &BytesStart) {
+        if self.skip > 0 {
+            return;
+        }
+        match e.local_name().as_ref().to_ascii_lowercase().as_slice() {
+            b"br" => {
+                if self.code > 0 {
+                    self.out().push('\n');
+                } else if matches!(self.frames.last(), Some((Capture::Cell, _))) {
+                    self.out().push_str("<br>");
+                } else if self.at_line_start() {
+                    // `<div><br/></div>` is how Evernote writes an empty line
+                    let out = self.out();
+                    if !out.is_empty() && !out.ends_with("\n\n") {
+                        out.push('\n');
+                    }
+                } else {
+                    self.out().push('\n');
+                }
+            }
+            b"hr" => {
+                self.blank_line();
+                self.inline("---");
+                self.blank_line();
+            }
+            b"en-todo" => {
+                let checked = attr_value(e, b"checked").as_deref() == Some("true");
+                let mark = if checked { "[x] " } else { "[ ] " };
+                if self.item_start {
+                    self.inline(mark);
+                } else {
+                    self.line_break();
+                    self.inline(&format!("- {}", mark));
+                }
+            }
+            b"en-media" => {
+                let mime = attr_value(e, b"type").unwrap_or_default();
+                let name = attr_value(e, b"hash").and_then(|hash| self.media.get(&hash.to_ascii_lowercase()).cloned());
+                let placeholder = match name {
+                    Some(name) => format!("[添付ファイル: {} ({})]", name, mime),
+                    None => format!("[添付ファイル: {}]", mime),
+                };
+                self.inline(&placeholder);
+            }
+            b"img" => {
+                let src = attr_value(e, b"src").unwrap_or_default();
+                if !src.is_empty() && !src.starts_with("data:") {
+                    let alt = attr_value(e, b"alt").unwrap_or_default();
+                    self.inline(&format!("![{}]({})", alt, src));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self) {
+        match self.closers.pop().unwrap_or(Close::Nothing) {
+            Close::Nothing => {}
+            Close::Block => self.line_break(),
+            Close::Paragraph => self.blank_line(),
+            Close::CodeLine => {
+                if !self.at_line_start() {
+                    self.out().push('\n');
+                }
+            }
+            Close::Code => {
+                if !self.at_line_start() {
+                    self.out().push('\n');
+                }
+                self.out().push_str("```");
+                self.code -= 1;
+                self.blank_line();
+            }
+            Close::Wrap(marker) => self.out().push_str(marker),
+            Close::List => {
+                self.lists.pop();
+                self.item_start = false;
+                if self.lists.is_empty() {
+                    self.blank_line();
+                } else {
+                    self.line_break();
+                }
+            }
+            Close::Item => {
+                self.item_start = false;
+                self.line_break();
+            }
+            Close::Table => {
+                let rows = self.tables.pop().unwrap_or_default();
+                let table = render_table(&rows);
+                self.inline(&table);
+                self.blank_line();
+            }
+            Close::Row => {}
+            Close::Frame => self.close_frame(),
+            Close::Skip => {
+                if self.skip > 0 {
+                    self.skip -= 1;
+                }
+            }
+        }
+    }
+
+    fn close_frame(&mut self) {
+        let Some((capture, buf)) = self.frames.pop() else {
+            return;
+        };
+        let text = buf.trim();
+        match capture {
+            Capture::Document => self.frames.push((capture, buf)),
+            Capture::Link(href) => {
+                if href.is_empty() {
+                    self.text(text);
+                } else if text.is_empty() || text == href {
+                    self.inline(&format!("<{}>", href));
+                } else {
+                    self.inline(&format!("[{}]({})", text.replace(['[', ']'], ""), href.replace(' ', "%20")));
+                }
+            }
+            Capture::Quote => {
+                let quoted: Vec<String> = text
+                    .lines()
+                    .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                    .collect();
+                self.inline(&quoted.join("\n"));
+                self.blank_line();
+            }
+            Capture::Cell => {
+                let cell = text.replace('\n', "<br>").replace('|', "\\|");
+                if let Some(row) = self.tables.last_mut().and_then(|t| t.last_mut()) {
+                    row.push(cell);
+                } else {
+                    self.text(&cell);
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        while self.frames.len() > 1 {
+            self.close_frame();
+        }
+        let mut out = String::new();
+        for line in self.out().lines() {
+            if line.trim().is_empty() && out.ends_with("\n\n") {
+                continue;
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out.trim().to_string()
+    }
+}
+
+/// GFM table with the first row as the header.
+fn render_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let line = |cells: &[String]| {
+        let padded: Vec<&str> = (0..columns).map(|i| cells.get(i).map(String::as_str).unwrap_or("")).collect();
+        format!("| {} |\n", padded.join(" | "))
+    };
+    let mut out = line(&rows[0]);
+    out.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+    for row in &rows[1..] {
+        out.push_str(&line(row));
+    }
+    out
+}
+
+/// Convert a note's ENML to Markdown. `media` maps resource hashes to file names.
+fn enml_to_markdown(enml: &str, media: HashMap<String, String>) -> Result<String, String> {
+    let mut reader = Reader::from_str(enml);
+    let mut converter = EnmlConverter::new(media);
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(e) => converter.start(&e),
+            Event::Empty(e) => converter.empty(&e),
+            Event::End(_) => converter.end(),
+            Event::Text(e) => {
+                let text = e.unescape_with(resolve_html5_entity).map_err(|e| e.to_string())?;
+                converter.text(&text);
+            }
+            Event::CData(e) => converter.text(&String::from_utf8_lossy(&e.into_inner())),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(converter.finish())
+}
+
+#[derive(Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created_at: Option<i64>,
+    updated_at: Option<i64>,
+    tags: Vec<String>,
+    /// File names of the note's resources by hash, for the `en-media` placeholders.
+    media: HashMap<String, String>,
+}
+
+/// The hash `en-media` refers to a resource by: MD5 of the decoded `<data>`, in hex.
+fn media_hash(data: &[u8]) -> Option<String> {
+    let encoded: Vec<u8> = data.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let decoded = STANDARD.decode(encoded).ok()?;
+    Some(format!("{:x}", md5::compute(decoded)))
+}
+
+/// Stream the `<note>` elements of an ENEX file into import entries, recording skipped attachments.
+fn parse_enex(path: &Path, now: i64, summary: &mut EnexImportSummary) -> Result<Vec<ImportEntry>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = Reader::from_reader(BufReader::new(file));
+
+    let mut entries = Vec::new();
+    let mut buf = Vec::new();
+    let mut stack: Vec<Vec<u8>> = Vec::new();
+    let mut text = String::new();
+    let mut note: Option<EnexNote> = None;
+    let mut resource: Option<EnexAttachment> = None;
+    let mut resource_hash: Option<String> = None;
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(|e| format!("Invalid ENEX: {}", e))?;
+        match event {
+            Event::Start(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"note" => note = Some(EnexNote::default()),
+                    b"resource" if note.is_some() => {
+                        resource = Some(EnexAttachment {
+                            note_title: String::new(),
+                            file_name: None,
+                            mime: String::new(),
+                        });
+                        resource_hash = None;
+                    }
+                    _ => {}
+                }
+                stack.push(name);
+                text.clear();
+            }
+            // A resource payload is read whole into `buf`, but only hashed, never copied into `text`
+            Event::Text(e) if resource.is_some() && stack.last().is_some_and(|n| n.as_slice() == b"data") => {
+                resource_hash = media_hash(&e.into_inner());
+            }
+            Event::Text(e) if note.is_some() && stack.last().is_some_and(|n| n.as_slice() != b"data") => {
+                text.push_str(&e.unescape_with(resolve_html5_entity).map_err(|e| format!("Invalid ENEX: {}", e))?);
+            }
+            Event::CData(e) if note.is_some() => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+            Event::End(_) => {
+                let name = stack.pop().unwrap_or_default();
+                let field = std::mem::take(&mut text);
+                match (name.as_slice(), note.as_mut(), resource.as_mut()) {
+                    (b"mime", Some(_), Some(res)) => res.mime = field.trim().to_string(),
+                    (b"file-name", Some(_), Some(res)) => res.file_name = Some(field.trim().to_string()),
+                    (b"resource", Some(n), Some(_)) => {
+                        let mut res = resource.take().expect("resource");
+                        res.note_title = n.title.clone();
+                        if let (Some(hash), Some(name)) = (resource_hash.take(), res.file_name.clone()) {
+                            n.media.insert(hash, name);
+                        }
+                        summary.attachments.push(res);
+                    }
+                    (b"title", Some(n), None) => n.title = field.trim().to_string(),
+                    (b"content", Some(n), None) => n.content = field.trim().to_string(),
+                    (b"created", Some(n), None) => n.created_at = parse_enex_time(&field),
+                    (b"updated", Some(n), None) => n.updated_at = parse_enex_time(&field),
+                    (b"tag", Some(n), None) if !field.trim().is_empty() => n.tags.push(field.trim().to_string()),
+                    (b"note", Some(_), _) => {
+                        let n = note.take().expect("note");
+                        entries.push(note_entry(n, now, summary));
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(entries)
+}
+
+fn note_entry(note: EnexNote, now: i64, summary: &mut EnexImportSummary) -> ImportEntry {
+    let title = if note.title.is_empty() { "無題".to_string() } else { note.title };
+    let body = match enml_to_markdown(&note.content, note.media) {
+        Ok(markdown) => markdown,
+        Err(_) => {
+            summary.unconverted.push(title.clone());
+            format!("```html\n{}\n```", note.content.trim())
+        }
+    };
+    let content = if note.tags.is_empty() {
+        body
+    } else {
+        format!("{}{}", crate::frontmatter::render(&[("tags", crate::frontmatter::render_list(&note.tags))]), body)
+    };
+
+    let created_at = note.created_at.unwrap_or(now);
+    ImportEntry {
+        title,
+        content,
+        created_at,
+        updated_at: note.updated_at.unwrap_or(created_at),
+        is_pinned: false,
+        is_markdown_view: false,
+        children: Vec::new(),
+    }
+}
+
+/// Import an Evernote export (`.enex`) under `target_parent_id`: one note per entry, collected
+/// under a parent named after the notebook (the file name). ENML becomes Markdown; timestamps
+/// are kept and tags go to the `tags` frontmatter. Attachments are reported, not imported.
+/// Emits `enex-import-progress` while inserting; the whole import is one transaction.
+#[tauri::command]
+pub async fn import_enex(
+    app: AppHandle,
+    path: String,
+    target_parent_id: Option<String>,
+) -> Result<EnexImportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let parent_id_int = parse_root_id(target_parent_id).map_err(|_| "Invalid Parent ID".to_string())?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        let file = Path::new(&path);
+        let notebook = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "Evernote".to_string());
+
+        let mut summary = EnexImportSummary {
+            notebook_id: String::new(),
+            notes: 0,
+            attachments: Vec::new(),
+            unconverted: Vec::new(),
+        };
+        let notes = parse_enex(file, now, &mut summary)?;
+        summary.notes = notes.len();
+        let entry = ImportEntry {
+            title: notebook,
+            content: String::new(),
+            created_at: now,
+            updated_at: now,
+            is_pinned: false,
+            is_markdown_view: false,
+            children: notes,
+        };
+
+        let state = app.state::<AppState>();
+        let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let total = entry.count();
+        let mut processed = 0;
+        let mut progress = || {
+            processed += 1;
+            if processed % 100 == 0 || processed == total {
+                let _ = app.emit("enex-import-progress", ImportProgress { processed, total });
+            }
+        };
+        let ids = insert_import(&tx, std::slice::from_ref(&entry), parent_id_int, now, &mut progress)?;
+
+        tx.commit().map_err(|e| e.to_string())?;
+
+        summary.notebook_id = ids.first().map(|id| id.to_string()).unwrap_or_default();
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(body: &str) -> String {
+        enml_to_markdown(&format!("<en-note>{}</en-note>", body), HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn enex_time_is_utc_millis() {
+        assert_eq!(parse_enex_time("19700101T000000Z"), Some(0));
+        assert_eq!(parse_enex_time("20240229T123456Z"), Some(1_709_210_096_000));
+        assert_eq!(parse_enex_time(" 20231231T235959Z\n"), Some(1_704_067_199_000));
+        assert_eq!(parse_enex_time("2023-12-31T23:59:59Z"), None);
+        assert_eq!(parse_enex_time("20231231T235959"), None);
+    }
+
+    #[test]
+    fn lists_and_checklists() {
+        assert_eq!(
+            convert("<ul><li>one<ul><li>nested</li></ul></li><li>two</li></ul><ol><li>first</li><li>second</li></ol>"),
+            "- one\n  - nested\n- two\n\n1. first\n2. second"
+        );
+        assert_eq!(
+            convert(
+                "<ul style=\"--en-todo: true;\"><li style=\"--en-checked: true;\">done</li>\
+                 <li style=\"--en-checked: false;\">open</li></ul>"
+            ),
+            "- [x] done\n- [ ] open"
+        );
+        assert_eq!(
+            convert("<div><en-todo checked=\"true\"/>bought milk</div><div><en-todo/>call back</div>"),
+            "- [x] bought milk\n- [ ] call back"
+        );
+    }
+
+    #[test]
+    fn blocks_links_and_tables() {
+        assert_eq!(
+            convert("<div>first</div><div><br/></div><div>after a blank line</div>"),
+            "first\n\nafter a blank line"
+        );
+        assert_eq!(
+            convert("<div>see <a href=\"https://example.com/a b\">the site</a> or <a href=\"https://x.test\">https://x.test</a></div>"),
+            "see [the site](https://example.com/a%20b) or <https://x.test>"
+        );
+        assert_eq!(
+            convert("<div style=\"-en-codeblock: true;\"><div>fn main() {</div><div>    &lt;tag&gt;</div><div>}</div></div>"),
+            "```\nfn main() {\n    <tag>\n}\n```"
+        );
+        assert_eq!(
+            convert("<table><tr><td>a</td><td>b|c</td></tr><tr><td>1</td><td>2<br/>3</td></tr></table>"),
+            "| a | b\\|c |\n| --- | --- |\n| 1 | 2<br>3 |"
+        );
+    }
+
+    #[test]
+    fn media_placeholders_resolve_by_hash() {
+        let mut media = HashMap::new();
+        media.insert("0cc175b9c0f1b6a831c399e269772661".to_string(), "a.png".to_string());
+        let markdown = enml_to_markdown(
+            "<en-note><div><en-media type=\"image/png\" hash=\"0CC175B9C0F1B6A831C399E269772661\"/></div>\
+             <div><en-media type=\"application/pdf\" hash=\"ffff\"/></div></en-note>",
+            media,
+        )
+        .unwrap();
+        assert_eq!(markdown, "[添付ファイル: a.png (image/png)]\n[添付ファイル: application/pdf]");
+    }
+
+    #[test]
+    fn enex_file_becomes_entries() {
+        let enex = r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>Trip</title>
+    <content><![CDATA[<?xml version="1.0" encoding="UTF-8"?><en-note><div>Packing</div><en-media type="text/plain" hash="0cc175b9c0f1b6a831c399e269772661"/></en-note>]]></content>
+    <created>20240101T090000Z</created>
+    <tag>travel</tag>
+    <tag>two words</tag>
+    <resource>
+      <data encoding="base64">
+YQ==
+      </data>
+      <mime>text/plain</mime>
+      <resource-attributes><file-name>list.txt</file-name></resource-attributes>
+    </resource>
+  </note>
+  <note>
+    <title></title>
+    <content><![CDATA[<en-note>plain</en-note>]]></content>
+  </note>
+</en-export>
+"#;
+        let path = std::env::temp_dir().join(format!("ultra-memo-enex-{}.enex", std::process::id()));
+        std::fs::write(&path, enex).unwrap();
+        let mut summary = EnexImportSummary {
+            notebook_id: String::new(),
+            notes: 0,
+            attachments: Vec::new(),
+            unconverted: Vec::new(),
+        };
+        let entries = parse_enex(&path, 42, &mut summary).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Trip");
+        assert_eq!(
+            entries[0].content,
+            "---\ntags: [\"travel\",\"two words\"]\n---\nPacking\n[添付ファイル: list.txt (text/plain)]"
+        );
+        assert_eq!(entries[0].created_at, 1_704_099_600_000);
+        assert_eq!(entries[0].updated_at, entries[0].created_at);
+        assert_eq!(entries[1].title, "無題");
+        assert_eq!(entries[1].content, "plain");
+        assert_eq!(entries[1].created_at, 42);
+
+        assert_eq!(summary.attachments.len(), 1);
+        assert_eq!(summary.attachments[0].note_title, "Trip");
+        assert_eq!(summary.attachments[0].file_name.as_deref(), Some("list.txt"));
+        assert_eq!(summary.attachments[0].mime, "text/plain");
+        assert!(summary.unconverted.is_empty());
+    }
+}
@@ -1,66 +1,294 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
 use tauri::State;
 use crate::AppState;
+use crate::commands::sort::apply_sort_settings;
+use crate::commands::tree::{
+    parse_root_id, tree_node_from_row, SCOPE_CTE, SCOPE_FILTER, SIBLING_ORDER, TREE_NODE_COLUMNS,
+};
+use rusqlite::Connection;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MarkdownExportOptions {
+    /// Deepest level to include, counted from the export's top level (0 = top level only).
+    pub max_depth: Option<usize>,
+    /// Extra `#`s added to every note heading.
+    pub heading_offset: usize,
+    /// Emit only the titles, without note content.
+    pub titles_only: bool,
+    /// Prepend a linked table of contents.
+    pub include_toc: bool,
+    /// Skip notes (with their subtrees) carrying any of these tags.
+    pub exclude_tags: Vec<String>,
+    /// Render levels past `######` as nested lists instead of clamping them.
+    pub deep_levels_as_list: bool,
+}
+
+pub(crate) struct NoteRow {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub title: String,
+    pub content: String,
+    pub order_key: f64,
+    pub is_pinned: bool,
+    pub is_markdown_view: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Live notes for an export, plus the parent the export starts from.
+/// Notes are grouped by parent and each group is in the tree's display order (pinned first,
+/// then the parent's sort setting), which every exporter follows.
+/// With `root_id` only that subtree is loaded, and the start is the root's parent,
+/// where the root is the only note in scope.
+pub(crate) fn load_note_rows(conn: &Connection, root_id: Option<i64>) -> Result<(Vec<NoteRow>, Option<i64>), String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH RECURSIVE {}
+             SELECT {}, n.content
+             FROM notes n WHERE n.is_deleted = 0 AND {}
+             ORDER BY n.parent_id, {}",
+            SCOPE_CTE, TREE_NODE_COLUMNS, SCOPE_FILTER, SIBLING_ORDER
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([root_id], |row| {
+            let note = NoteRow {
+                id: row.get(0)?,
+                parent_id: row.get(1)?,
+                title: row.get(2)?,
+                content: row.get(13)?,
+                order_key: row.get(5)?,
+                is_pinned: row.get::<_, i64>(7)? != 0,
+                is_markdown_view: row.get::<_, i64>(8)? != 0,
+                created_at: row.get(9)?,
+                updated_at: row.get(10)?,
+            };
+            Ok((tree_node_from_row(row)?, note))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut ordered = Vec::new();
+    for row in rows {
+        ordered.push(row.map_err(|e| e.to_string())?);
+    }
+    apply_sort_settings(conn, &mut ordered, |(node, _)| node)?;
+    let notes: Vec<NoteRow> = ordered.into_iter().map(|(_, note)| note).collect();
+
+    let start = match root_id {
+        Some(root) => notes
+            .iter()
+            .find(|n| n.id == root)
+            .ok_or("Note not found")?
+            .parent_id,
+        None => None,
+    };
 
-struct NoteRow {
-    id: i64,
+    Ok((notes, start))
+}
+
+/// Depth-first walk of `parent_id`'s descendants in the order `notes` lists them (display order
+/// for `load_note_rows`), calling `visit(note, depth)`.
+/// A note's children are skipped when `visit` returns false.
+pub(crate) fn walk_tree<'a>(
+    notes: &'a [NoteRow],
     parent_id: Option<i64>,
-    title: String,
-    content: String,
-    order_key: f64,
+    depth: usize,
+    visit: &mut dyn FnMut(&'a NoteRow, usize) -> bool,
+) {
+    let children = notes.iter().filter(|n| n.parent_id == parent_id);
+    for child in children {
+        if visit(child, depth) {
+            walk_tree(notes, Some(child.id), depth + 1, visit);
+        }
+    }
+}
+
+/// Title as a heading at `depth` (capped at `######`) followed by the note's content.
+pub(crate) fn push_note_markdown(output: &mut String, title: &str, content: &str, depth: usize) {
+    let level = (depth + 1).min(6);
+    let hashes = "#".repeat(level);
+    output.push_str(&format!("{} {}\n\n", hashes, title));
+    if !content.is_empty() {
+        output.push_str(content);
+        if !content.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+}
+
+/// Lowercased tags of a note: the frontmatter `tags` list plus inline `#tags` outside code blocks.
+pub(crate) fn note_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let (pairs, body) = crate::frontmatter::parse(content);
+    if let Some((_, value)) = pairs.iter().find(|(k, _)| k.eq_ignore_ascii_case("tags")) {
+        tags.extend(
+            crate::frontmatter::list(value)
+                .iter()
+                .map(|t| t.trim_start_matches('#').to_lowercase())
+                .filter(|t| !t.is_empty()),
+        );
+    }
+
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        for word in line.split_whitespace() {
+            if let Some(tag) = word.strip_prefix('#') {
+                let tag = tag.trim_end_matches(|c: char| c.is_ascii_punctuation() && c != '_' && c != '-' && c != '/');
+                if !tag.is_empty() && !tag.starts_with('#') {
+                    tags.push(tag.to_lowercase());
+                }
+            }
+        }
+    }
+    tags
+}
+
+/// GitHub-style heading anchor, made unique against `used`.
+fn heading_slug(title: &str, used: &mut HashSet<String>) -> String {
+    let base: String = title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect();
+    let mut slug = base.clone();
+    let mut n = 1;
+    while !used.insert(slug.clone()) {
+        slug = format!("{}-{}", base, n);
+        n += 1;
+    }
+    slug
+}
+
+fn escape_link_text(title: &str) -> String {
+    title.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]")
 }
 
-fn build_markdown(notes: &[NoteRow], parent_id: Option<i64>, depth: usize) -> String {
-    let mut children: Vec<&NoteRow> = notes
+/// `title` as a list item `indent` levels deep, with the content indented under it.
+fn push_note_list_item(output: &mut String, title: &str, content: &str, indent: usize) {
+    let pad = "  ".repeat(indent);
+    output.push_str(&format!("{}- {}\n", pad, title));
+    for line in content.lines() {
+        if line.is_empty() {
+            output.push('\n');
+        } else {
+            output.push_str(&format!("{}  {}\n", pad, line));
+        }
+    }
+    if !content.is_empty() {
+        output.push('\n');
+    }
+}
+
+fn build_markdown(notes: &[NoteRow], parent_id: Option<i64>, options: &MarkdownExportOptions) -> String {
+    let excluded: HashSet<String> = options
+        .exclude_tags
         .iter()
-        .filter(|n| n.parent_id == parent_id)
+        .map(|t| t.trim().trim_start_matches('#').to_lowercase())
+        .filter(|t| !t.is_empty())
         .collect();
-    children.sort_by(|a, b| a.order_key.partial_cmp(&b.order_key).unwrap());
+
+    let mut included: Vec<(&NoteRow, usize)> = Vec::new();
+    walk_tree(notes, parent_id, 0, &mut |note, depth| {
+        if options.max_depth.is_some_and(|max| depth > max) {
+            return false;
+        }
+        if !excluded.is_empty() && note_tags(&note.content).iter().any(|t| excluded.contains(t)) {
+            return false;
+        }
+        included.push((note, depth));
+        true
+    });
 
     let mut output = String::new();
-    for child in children {
-        let level = (depth + 1).min(6);
-        let hashes = "#".repeat(level);
-        output.push_str(&format!("{} {}\n\n", hashes, child.title));
-        if !child.content.is_empty() {
-            output.push_str(&child.content);
-            if !child.content.ends_with('\n') {
-                output.push('\n');
+    let as_list = |depth: usize| options.deep_levels_as_list && depth + options.heading_offset >= 6;
+
+    if options.include_toc {
+        let mut used = HashSet::new();
+        let toc_level = (options.heading_offset + 1).min(6);
+        output.push_str(&format!("{} 目次\n\n", "#".repeat(toc_level)));
+        heading_slug("目次", &mut used);
+        for (note, depth) in &included {
+            let pad = "  ".repeat(*depth);
+            if as_list(*depth) {
+                output.push_str(&format!("{}- {}\n", pad, note.title));
+            } else {
+                let slug = heading_slug(&note.title, &mut used);
+                output.push_str(&format!("{}- [{}](#{})\n", pad, escape_link_text(&note.title), slug));
             }
-            output.push('\n');
         }
-        output.push_str(&build_markdown(notes, Some(child.id), depth + 1));
+        output.push('\n');
+    }
+
+    for (note, depth) in included {
+        // Tags were read above; the combined document carries only each note's body
+        let content = if options.titles_only { "" } else { crate::frontmatter::parse(&note.content).1 };
+        let level_depth = depth + options.heading_offset;
+        if as_list(depth) {
+            push_note_list_item(&mut output, &note.title, content, level_depth - 6);
+        } else {
+            push_note_markdown(&mut output, &note.title, content, level_depth);
+        }
     }
     output
 }
 
+/// Export the whole tree, or just the subtree under `root_id` (root included), as one Markdown document.
 #[tauri::command]
-pub fn export_markdown_tree(state: State<'_, AppState>) -> Result<String, String> {
+pub fn export_markdown_tree(
+    state: State<'_, AppState>,
+    root_id: Option<String>,
+    options: Option<MarkdownExportOptions>,
+) -> Result<String, String> {
     let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let (notes, start) = load_note_rows(&conn, parse_root_id(root_id)?)?;
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, parent_id, title, content, order_key
-             FROM notes WHERE is_deleted = 0
-             ORDER BY parent_id, order_key",
-        )
-        .map_err(|e| e.to_string())?;
+    Ok(build_markdown(&notes, start, &options.unwrap_or_default()))
+}
 
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(NoteRow {
-                id: row.get(0)?,
-                parent_id: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-                order_key: row.get(4)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_test_note, open_in_memory};
 
-    let mut notes = Vec::new();
-    for row in rows {
-        notes.push(row.map_err(|e| e.to_string())?);
-    }
+    #[test]
+    fn note_rows_follow_display_order() {
+        let conn = open_in_memory().unwrap();
+        let parent = insert_test_note(&conn, None, "Parent", "", 1024.0, false);
+        insert_test_note(&conn, None, "Pinned", "", 2048.0, true);
+        insert_test_note(&conn, Some(parent), "b10", "", 1024.0, false);
+        insert_test_note(&conn, Some(parent), "b2", "", 2048.0, false);
+        insert_test_note(&conn, Some(parent), "z", "", 3072.0, true);
+        insert_test_note(&conn, Some(parent), "a", "", 4096.0, false);
+        conn.execute(
+            "INSERT INTO sort_settings (parent_key, mode, property, descending, updated_at) VALUES (?, 'title', NULL, 0, 0)",
+            [parent],
+        )
+        .unwrap();
 
-    Ok(build_markdown(&notes, None, 0))
+        let (notes, start) = load_note_rows(&conn, None).unwrap();
+        let mut titles = Vec::new();
+        walk_tree(&notes, start, 0, &mut |note, _| {
+            titles.push(note.title.as_str());
+            true
+        });
+        assert_eq!(titles, ["Pinned", "Parent", "z", "a", "b2", "b10"]);
+    }
 }
@@ -0,0 +1,488 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::AppState;
+use crate::commands::export::{load_note_rows, NoteRow};
+use crate::commands::sort::natural_cmp;
+use crate::commands::tree::parse_root_id;
+use crate::naming::unique_name;
+use crate::ordering::{self, ORDER_STEP};
+use rusqlite::{params, Connection};
+
+const MAX_NAME_CHARS: usize = 80;
+
+/// Frontmatter keys written by `note_frontmatter`; a block opening with all of them is ours on import.
+const METADATA_KEYS: [&str; 6] = ["id", "title", "created_at", "updated_at", "pinned", "markdown_view"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderExportSummary {
+    pub path: String,
+    pub notes: usize,
+    pub directories: usize,
+}
+
+/// File-system-safe stem for a note title.
+pub(crate) fn sanitize_file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').trim_end();
+    if cleaned.is_empty() {
+        "無題".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Frontmatter carrying everything the file name can't: the exact title and note metadata.
+pub(crate) fn note_frontmatter(note: &NoteRow) -> String {
+    let values = [
+        note.id.to_string(),
+        note.title.clone(),
+        note.created_at.to_string(),
+        note.updated_at.to_string(),
+        note.is_pinned.to_string(),
+        note.is_markdown_view.to_string(),
+    ];
+    let pairs: Vec<(&str, String)> = METADATA_KEYS.iter().copied().zip(values).collect();
+    crate::frontmatter::render(&pairs)
+}
+
+/// Siblings grouped by parent, in the display order `load_note_rows` returns them;
+/// shared by the file-based exporters.
+pub(crate) fn children_by_parent(notes: &[NoteRow]) -> HashMap<Option<i64>, Vec<&NoteRow>> {
+    let mut children: HashMap<Option<i64>, Vec<&NoteRow>> = HashMap::new();
+    for note in notes {
+        children.entry(note.parent_id).or_default().push(note);
+    }
+    children
+}
+
+/// Lay out `parent_id`'s descendants under `dir` in tree order as `NNN-Title.md`, with a
+/// `NNN-Title/` directory next to each note that has children. The prefix keeps the tree order.
+/// Paths are relative and `/`-separated; shared by the folder and zip exporters.
+pub(crate) fn layout_level<'a>(
+    children: &HashMap<Option<i64>, Vec<&'a NoteRow>>,
+    parent_id: Option<i64>,
+    dir: &str,
+    files: &mut Vec<(String, &'a NoteRow)>,
+) {
+    let Some(siblings) = children.get(&parent_id) else {
+        return;
+    };
+    let width = siblings.len().to_string().len().max(3);
+
+    let mut used = HashSet::new();
+    for (i, note) in siblings.iter().enumerate() {
+        let stem = format!("{:0width$}-{}", i + 1, sanitize_file_name(&note.title), width = width);
+        let stem = format!("{}{}", dir, unique_name(&mut used, stem));
+        files.push((format!("{}.md", stem), note));
+
+        if children.contains_key(&Some(note.id)) {
+            layout_level(children, Some(note.id), &format!("{}/", stem), files);
+        }
+    }
+}
+
+/// A note as written by the exporters: one frontmatter block with the metadata lines first
+/// and the note's own frontmatter lines after them, then the rest of the content verbatim.
+pub(crate) fn note_file(note: &NoteRow) -> String {
+    let (user_lines, body) = crate::frontmatter::block(&note.content).unwrap_or(("", &note.content));
+    let mut file = note_frontmatter(note);
+    file.truncate(file.len() - "---\n".len());
+    file.push_str(user_lines);
+    file.push_str("---\n");
+    file.push_str(body);
+    file
+}
+
+/// Write `start`'s descendants to `output_dir`, which must be empty or not exist yet.
+fn write_folder(output_dir: String, notes: &[NoteRow], start: Option<i64>) -> Result<FolderExportSummary, String> {
+    let dir = PathBuf::from(&output_dir);
+    if dir.exists() {
+        let mut entries = fs::read_dir(&dir).map_err(|e| e.to_string())?;
+        if entries.next().is_some() {
+            return Err("Output directory is not empty".into());
+        }
+    } else {
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    }
+
+    let mut summary = FolderExportSummary {
+        path: output_dir,
+        notes: 0,
+        directories: 0,
+    };
+    let mut files = Vec::new();
+    layout_level(&children_by_parent(notes), start, "", &mut files);
+    for (path, note) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent().filter(|p| !p.exists()) {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            summary.directories += 1;
+        }
+        fs::write(&path, note_file(note)).map_err(|e| e.to_string())?;
+        summary.notes += 1;
+    }
+    Ok(summary)
+}
+
+/// Export the tree (or the subtree under `root_id`) to `output_dir` as one Markdown file per note,
+/// nested in directories that mirror the hierarchy. `output_dir` must be empty or not exist yet.
+#[tauri::command]
+pub async fn export_markdown_folder(
+    app: AppHandle,
+    output_dir: String,
+    root_id: Option<String>,
+) -> Result<FolderExportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root_id_int = parse_root_id(root_id)?;
+        let (notes, start) = {
+            let state = app.state::<AppState>();
+            let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+            load_note_rows(&conn, root_id_int)?
+        };
+
+        write_folder(output_dir, &notes, start)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderImportSummary {
+    pub dry_run: bool,
+    pub notes: usize,
+    /// Parent notes created for directories that had no matching `.md` file.
+    pub directories: usize,
+    /// Paths (relative to the import root) that were not imported.
+    pub skipped: Vec<String>,
+    /// Top-level notes created under the target parent (empty for a dry run).
+    pub root_ids: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// A note to create on import, with its children; shared by the file-based importers.
+pub(crate) struct ImportEntry {
+    pub title: String,
+    pub content: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub is_pinned: bool,
+    pub is_markdown_view: bool,
+    pub children: Vec<ImportEntry>,
+}
+
+impl ImportEntry {
+    pub(crate) fn count(&self) -> usize {
+        1 + self.children.iter().map(ImportEntry::count).sum::<usize>()
+    }
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"))
+}
+
+fn modified_millis(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+/// Build a note from a Markdown file. Exported metadata comes from the frontmatter,
+/// falling back to the file stem and modification time.
+fn read_note_file(path: &Path, stem: &str, now: i64) -> Option<ImportEntry> {
+    let raw = fs::read_to_string(path).ok()?;
+    let (pairs, content) = split_note_file(&raw);
+    let value = |key: &str| pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    let mtime = modified_millis(path).unwrap_or(now);
+    let created_at = value("created_at").and_then(|v| v.parse().ok()).unwrap_or(mtime);
+    let updated_at = value("updated_at").and_then(|v| v.parse().ok()).unwrap_or(mtime);
+
+    Some(ImportEntry {
+        title: value("title").map(str::to_string).unwrap_or_else(|| stem.to_string()),
+        content,
+        created_at,
+        updated_at,
+        is_pinned: value("pinned") == Some("true"),
+        is_markdown_view: value("markdown_view") == Some("true"),
+        children: Vec::new(),
+    })
+}
+
+/// Split a file written by `note_file` into its metadata pairs and the note content.
+/// The block counts as ours only when it opens with every metadata key in order; those lines
+/// are removed and the user's own frontmatter lines (tags, properties…) stay with the content.
+/// Any other file comes back whole with no metadata.
+pub(crate) fn split_note_file(raw: &str) -> (Vec<(String, String)>, String) {
+    let Some((lines, body)) = crate::frontmatter::block(raw) else {
+        return (Vec::new(), raw.to_string());
+    };
+    let lines: Vec<&str> = lines.split_inclusive('\n').collect();
+    let ours = lines.len() >= METADATA_KEYS.len()
+        && lines.iter().zip(METADATA_KEYS).all(|(line, key)| {
+            line.strip_prefix(key).is_some_and(|rest| rest.starts_with(':'))
+        });
+    if !ours {
+        return (Vec::new(), raw.to_string());
+    }
+
+    let metadata = crate::frontmatter::parse(raw).0.into_iter().take(METADATA_KEYS.len()).collect();
+    let user_lines = lines[METADATA_KEYS.len()..].concat();
+    let content = if user_lines.is_empty() {
+        body.to_string()
+    } else {
+        format!("---\n{}---\n{}", user_lines, body)
+    };
+    (metadata, content)
+}
+
+/// Read `dir` into import entries. `foo.md` next to a `foo/` directory becomes the parent of
+/// the directory's notes; a directory without a matching file gets an empty parent note.
+/// Entries are taken in natural file-name order, which keeps the exporter's `NNN-` order.
+fn scan_dir(
+    dir: &Path,
+    relative: &Path,
+    now: i64,
+    summary: &mut FolderImportSummary,
+) -> Result<Vec<ImportEntry>, String> {
+    let mut files: Vec<(String, PathBuf)> = Vec::new();
+    let mut dirs: HashMap<String, PathBuf> = HashMap::new();
+    let mut dir_names: Vec<String> = Vec::new();
+
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type().map_err(|e| e.to_string())?;
+        if file_type.is_dir() {
+            dirs.insert(name.clone(), path);
+            dir_names.push(name);
+        } else if file_type.is_file() && is_markdown_file(&path) {
+            files.push((name, path));
+        } else {
+            summary.skipped.push(relative.join(&name).to_string_lossy().to_string());
+        }
+    }
+
+    // (sort name, entry): a note file and its directory share a stem, so they land on one entry
+    let mut entries: Vec<(String, ImportEntry)> = Vec::new();
+    for (name, path) in files {
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let Some(mut entry) = read_note_file(&path, &stem, now) else {
+            summary.skipped.push(relative.join(&name).to_string_lossy().to_string());
+            continue;
+        };
+        if let Some(sub_dir) = dirs.remove(&stem) {
+            entry.children = scan_dir(&sub_dir, &relative.join(&stem), now, summary)?;
+        }
+        summary.notes += 1;
+        entries.push((stem, entry));
+    }
+    for name in dir_names {
+        let Some(sub_dir) = dirs.remove(&name) else {
+            continue;
+        };
+        let children = scan_dir(&sub_dir, &relative.join(&name), now, summary)?;
+        summary.directories += 1;
+        entries.push((
+            name.clone(),
+            ImportEntry {
+                title: name,
+                content: String::new(),
+                created_at: modified_millis(&sub_dir).unwrap_or(now),
+                updated_at: modified_millis(&sub_dir).unwrap_or(now),
+                is_pinned: false,
+                is_markdown_view: false,
+                children,
+            },
+        ));
+    }
+
+    entries.sort_by(|(a, _), (b, _)| natural_cmp(a, b).then_with(|| a.cmp(b)));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+fn insert_entries(
+    conn: &Connection,
+    entries: &[ImportEntry],
+    parent_id: Option<i64>,
+    keys: &[f64],
+    now: i64,
+    progress: &mut dyn FnMut(),
+) -> Result<Vec<i64>, String> {
+    let mut ids = Vec::new();
+    for (entry, key) in entries.iter().zip(keys) {
+        conn.execute(
+            "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_pinned, is_markdown_view, is_deleted, created_at, updated_at, structure_updated_at)
+             VALUES (?, ?, ?, ?, 0, ?, ?, 0, ?, ?, ?)",
+            params![
+                parent_id,
+                entry.title,
+                entry.content,
+                key,
+                entry.is_pinned,
+                entry.is_markdown_view,
+                entry.created_at,
+                entry.updated_at,
+                now
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let id = conn.last_insert_rowid();
+        ids.push(id);
+        progress();
+
+        let child_keys: Vec<f64> = (1..=entry.children.len()).map(|i| i as f64 * ORDER_STEP).collect();
+        insert_entries(conn, &entry.children, Some(id), &child_keys, now, progress)?;
+    }
+    Ok(ids)
+}
+
+/// Insert `entries` after `parent_id`'s existing children, in the caller's transaction.
+/// `progress` is called once per created note. Returns the ids of the top-level notes.
+pub(crate) fn insert_import(
+    conn: &Connection,
+    entries: &[ImportEntry],
+    parent_id: Option<i64>,
+    now: i64,
+    progress: &mut dyn FnMut(),
+) -> Result<Vec<i64>, String> {
+    if let Some(parent) = parent_id {
+        conn.query_row("SELECT 1 FROM notes WHERE id = ? AND is_deleted = 0", [parent], |_| Ok(()))
+            .map_err(|_| "Target parent not found")?;
+    }
+
+    let existing = ordering::child_ids(conn, parent_id, &[]).map_err(|e| e.to_string())?;
+    let keys = ordering::keys_between(conn, parent_id, existing.last().copied(), None, entries.len())
+        .map_err(|e| e.to_string())?;
+    insert_entries(conn, entries, parent_id, &keys, now, progress)
+}
+
+/// Import a folder of Markdown files (e.g. an Obsidian vault or an `export_markdown_folder`
+/// export) under `target_parent_id`, appended after its existing children.
+/// Content is kept verbatim, so `[[wiki links]]` keep resolving by title.
+/// With `dry_run` nothing is written and only the summary is returned.
+/// Emits `markdown-import-progress` while inserting; the whole import is one transaction.
+#[tauri::command]
+pub async fn import_markdown_folder(
+    app: AppHandle,
+    path: String,
+    target_parent_id: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<FolderImportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let parent_id_int = parse_root_id(target_parent_id).map_err(|_| "Invalid Parent ID".to_string())?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        let root = PathBuf::from(&path);
+        if !root.is_dir() {
+            return Err(format!("Not a directory: {}", path));
+        }
+
+        let mut summary = FolderImportSummary {
+            dry_run: dry_run.unwrap_or(false),
+            notes: 0,
+            directories: 0,
+            skipped: Vec::new(),
+            root_ids: Vec::new(),
+        };
+        let entries = scan_dir(&root, Path::new(""), now, &mut summary)?;
+        if summary.dry_run || entries.is_empty() {
+            return Ok(summary);
+        }
+
+        let state = app.state::<AppState>();
+        let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let total: usize = entries.iter().map(ImportEntry::count).sum();
+        let mut processed = 0;
+        let mut progress = || {
+            processed += 1;
+            if processed % 100 == 0 || processed == total {
+                let _ = app.emit("markdown-import-progress", ImportProgress { processed, total });
+            }
+        };
+        let ids = insert_import(&tx, &entries, parent_id_int, now, &mut progress)?;
+
+        tx.commit().map_err(|e| e.to_string())?;
+
+        summary.root_ids = ids.iter().map(|id| id.to_string()).collect();
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_test_note, open_in_memory, test_outline};
+
+    #[test]
+    fn folder_export_round_trips() {
+        let source = open_in_memory().unwrap();
+        let alpha = insert_test_note(&source, None, "Alpha", "---\ntags: [a, b]\n---\nAlpha body", 2048.0, false);
+        insert_test_note(&source, None, "Beta: \"quoted\"/slash", "Beta body\n", 1024.0, true);
+        insert_test_note(&source, Some(alpha), "Same", "second", 2048.0, false);
+        let first = insert_test_note(&source, Some(alpha), "Same", "first [[Alpha]]", 1024.0, true);
+        insert_test_note(&source, Some(first), "Leaf", "", 1024.0, false);
+        insert_test_note(&source, None, "Gamma", "Gamma body", 3072.0, false);
+        insert_test_note(&source, None, "Partial", "---\nid: 7\ntitle: Mine\n---\nPartial body", 4096.0, false);
+        insert_test_note(&source, None, "Yaml", "---\naliases:\n  - one\n---\n---\nnot: frontmatter\n---\n", 5120.0, false);
+
+        let dir = std::env::temp_dir().join(format!("ultra-memo-folder-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (notes, start) = load_note_rows(&source, None).unwrap();
+        let alpha_file = note_file(notes.iter().find(|note| note.id == alpha).unwrap());
+        assert!(alpha_file.ends_with("markdown_view: true\ntags: [a, b]\n---\nAlpha body"));
+        let exported = write_folder(dir.to_string_lossy().to_string(), &notes, start).unwrap();
+        assert_eq!(exported.notes, 8);
+
+        let mut summary = FolderImportSummary {
+            dry_run: false,
+            notes: 0,
+            directories: 0,
+            skipped: Vec::new(),
+            root_ids: Vec::new(),
+        };
+        let entries = scan_dir(&dir, Path::new(""), 0, &mut summary).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.notes, 8);
+        assert_eq!(summary.directories, 0);
+        assert!(summary.skipped.is_empty());
+
+        let mut target = open_in_memory().unwrap();
+        let tx = target.transaction().unwrap();
+        insert_import(&tx, &entries, None, 0, &mut || {}).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(test_outline(&target), test_outline(&source));
+    }
+}
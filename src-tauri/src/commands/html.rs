@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd, TextMergeStream};
+use tauri::State;
+use crate::AppState;
+use crate::commands::export::{load_note_rows, walk_tree, NoteRow};
+use crate::commands::links::wiki_link_regex;
+
+const EXPORT_CSS: &str = "
+body { margin: 0; background: #fff; color: #1f2328; font: 15px/1.7 -apple-system, BlinkMacSystemFont, 'Segoe UI', 'Hiragino Sans', 'Noto Sans JP', sans-serif; }
+main { max-width: 820px; margin: 0 auto; padding: 40px 24px 80px; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; margin: 1.6em 0 0.6em; }
+h1 { font-size: 1.9em; border-bottom: 1px solid #d0d7de; padding-bottom: 0.3em; }
+h2 { font-size: 1.5em; border-bottom: 1px solid #eaeef2; padding-bottom: 0.3em; }
+a { color: #0969da; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.missing-link { color: #8c959f; border-bottom: 1px dashed #8c959f; }
+code { font: 0.9em ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; background: #f6f8fa; padding: 0.15em 0.35em; border-radius: 4px; }
+pre { background: #f6f8fa; padding: 14px 16px; border-radius: 6px; overflow-x: auto; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; color: #59636e; border-left: 4px solid #d0d7de; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #d0d7de; padding: 6px 12px; }
+th { background: #f6f8fa; }
+ul.contains-task-list, li:has(> input[type=checkbox]) { list-style: none; }
+li > input[type=checkbox] { margin: 0 0.5em 0 -1.4em; }
+img { max-width: 100%; }
+hr { border: 0; border-top: 1px solid #d0d7de; margin: 2em 0; }
+";
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Markdown options for exported note content.
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES
+}
+
+/// Parse one note's content for export. Raw HTML is shown as text rather than passed through,
+/// and `[[Title]]` in text becomes an anchor to the exported note with that title (or a marked
+/// span when it isn't part of the export). Code, links and image alt text are left alone.
+fn note_events<'a>(content: &'a str, anchors: &HashMap<String, i64>) -> Vec<Event<'a>> {
+    let re = wiki_link_regex();
+    let mut events = Vec::new();
+    // Open code blocks, links and images: no wiki links inside them
+    let mut skip_depth = 0usize;
+
+    for event in TextMergeStream::new(Parser::new_ext(content, markdown_options())) {
+        match event {
+            Event::Start(tag @ (Tag::CodeBlock(_) | Tag::Link { .. } | Tag::Image { .. })) => {
+                skip_depth += 1;
+                events.push(Event::Start(tag));
+            }
+            Event::End(tag @ (TagEnd::CodeBlock | TagEnd::Link | TagEnd::Image)) => {
+                skip_depth = skip_depth.saturating_sub(1);
+                events.push(Event::End(tag));
+            }
+            Event::Start(Tag::HtmlBlock) => events.push(Event::Start(Tag::Paragraph)),
+            Event::End(TagEnd::HtmlBlock) => events.push(Event::End(TagEnd::Paragraph)),
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            Event::Text(text) if skip_depth == 0 && re.is_match(&text) => {
+                let mut last = 0;
+                for caps in re.captures_iter(&text) {
+                    let whole = caps.get(0).unwrap();
+                    if whole.start() > last {
+                        events.push(Event::Text(text[last..whole.start()].to_string().into()));
+                    }
+                    let title = caps[1].trim();
+                    let anchor = match anchors.get(&title.to_lowercase()) {
+                        Some(id) => format!("<a href=\"#note-{}\">{}</a>", id, escape_html(title)),
+                        None => format!("<span class=\"missing-link\">{}</span>", escape_html(title)),
+                    };
+                    events.push(Event::InlineHtml(anchor.into()));
+                    last = whole.end();
+                }
+                if last < text.len() {
+                    events.push(Event::Text(text[last..].to_string().into()));
+                }
+            }
+            event => events.push(event),
+        }
+    }
+    events
+}
+
+/// Render the note `root_id` (and its descendants when `include_children`) to a self-contained
+/// HTML document with inline CSS. Wiki links between exported notes become in-page anchors.
+#[tauri::command]
+pub fn export_html(state: State<'_, AppState>, root_id: String, include_children: bool) -> Result<String, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let root_id_int = root_id.parse::<i64>().map_err(|_| "Invalid Root ID")?;
+
+    let (mut notes, start) = load_note_rows(&conn, Some(root_id_int))?;
+    drop(conn);
+    if !include_children {
+        notes.retain(|n| n.id == root_id_int);
+    }
+
+    // First note in tree order wins when titles repeat, like `resolve_wiki_link`
+    let mut ordered: Vec<(&NoteRow, usize)> = Vec::new();
+    walk_tree(&notes, start, 0, &mut |note, depth| {
+        ordered.push((note, depth));
+        true
+    });
+    let mut anchors: HashMap<String, i64> = HashMap::new();
+    for (note, _) in &ordered {
+        anchors.entry(note.title.trim().to_lowercase()).or_insert(note.id);
+    }
+
+    let mut body = String::new();
+    for (note, depth) in &ordered {
+        let level = (depth + 1).min(6);
+        body.push_str(&format!("<div id=\"note-{}\" class=\"note-anchor\"></div>\n", note.id));
+        body.push_str(&format!("<h{0}>{1}</h{0}>\n", level, escape_html(&note.title)));
+        let (_, content) = crate::frontmatter::parse(&note.content);
+        html::push_html(&mut body, note_events(content, &anchors).into_iter());
+    }
+
+    let title = ordered.first().map(|(n, _)| n.title.as_str()).unwrap_or_default();
+    Ok(format!(
+        "<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape_html(title),
+        EXPORT_CSS,
+        body
+    ))
+}
@@ -0,0 +1,542 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use crate::AppState;
+use crate::ordering;
+use rusqlite::{params, Connection, Row};
+
+pub const BACKUP_FORMAT: &str = "ultra-memo-backup";
+/// Bump when the layout changes; older files must stay importable.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Every table the app owns, row for row. FTS and the tree change log are derived and not included.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    pub notes: Vec<BackupNote>,
+    #[serde(default)]
+    pub open_state: Vec<BackupOpenState>,
+    #[serde(default)]
+    pub saved_searches: Vec<BackupSavedSearch>,
+    #[serde(default)]
+    pub note_revisions: Vec<BackupRevision>,
+    #[serde(default)]
+    pub sort_settings: Vec<BackupSortSetting>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupNote {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub title: String,
+    pub content: String,
+    pub order_key: f64,
+    pub is_open: bool,
+    pub is_pinned: bool,
+    pub is_markdown_view: bool,
+    pub is_deleted: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub structure_updated_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupOpenState {
+    pub note_id: i64,
+    pub last_opened_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    /// Raw JSON, as stored.
+    pub filters: String,
+    pub sort: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupRevision {
+    pub id: i64,
+    pub note_id: i64,
+    pub title: String,
+    pub content: String,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSortSetting {
+    /// None for the root level.
+    pub parent_id: Option<i64>,
+    pub mode: String,
+    pub property: Option<String>,
+    pub descending: bool,
+    pub updated_at: i64,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupImportMode {
+    /// Recreate everything with the original ids; the database must be empty.
+    Restore,
+    /// Add the backup to the current notes, giving every row a new id.
+    Merge,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub notes: usize,
+    pub open_state: usize,
+    pub saved_searches: usize,
+    pub note_revisions: usize,
+    pub sort_settings: usize,
+}
+
+impl Backup {
+    fn summary(&self) -> BackupSummary {
+        BackupSummary {
+            notes: self.notes.len(),
+            open_state: self.open_state.len(),
+            saved_searches: self.saved_searches.len(),
+            note_revisions: self.note_revisions.len(),
+            sort_settings: self.sort_settings.len(),
+        }
+    }
+}
+
+fn query_all<T>(conn: &Connection, sql: &str, f: impl FnMut(&Row) -> rusqlite::Result<T>) -> Result<Vec<T>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], f).map_err(|e| e.to_string())?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(result)
+}
+
+fn read_backup(conn: &Connection, now: i64) -> Result<Backup, String> {
+    Ok(Backup {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        exported_at: now,
+        notes: query_all(
+            conn,
+            "SELECT id, parent_id, title, content, order_key, is_open, is_pinned, is_markdown_view,
+                    is_deleted, created_at, updated_at, structure_updated_at
+             FROM notes ORDER BY id",
+            |row| {
+                Ok(BackupNote {
+                    id: row.get(0)?,
+                    parent_id: row.get(1)?,
+                    title: row.get(2)?,
+                    content: row.get(3)?,
+                    order_key: row.get(4)?,
+                    is_open: row.get::<_, i64>(5)? != 0,
+                    is_pinned: row.get::<_, i64>(6)? != 0,
+                    is_markdown_view: row.get::<_, i64>(7)? != 0,
+                    is_deleted: row.get::<_, i64>(8)? != 0,
+                    created_at: row.get(9)?,
+                    updated_at: row.get(10)?,
+                    structure_updated_at: row.get(11)?,
+                })
+            },
+        )?,
+        open_state: query_all(conn, "SELECT note_id, last_opened_at FROM open_state ORDER BY note_id", |row| {
+            Ok(BackupOpenState {
+                note_id: row.get(0)?,
+                last_opened_at: row.get(1)?,
+            })
+        })?,
+        saved_searches: query_all(
+            conn,
+            "SELECT id, name, query, filters, sort, created_at, updated_at FROM saved_searches ORDER BY id",
+            |row| {
+                Ok(BackupSavedSearch {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    query: row.get(2)?,
+                    filters: row.get(3)?,
+                    sort: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                })
+            },
+        )?,
+        note_revisions: query_all(
+            conn,
+            "SELECT id, note_id, title, content, created_at FROM note_revisions ORDER BY id",
+            |row| {
+                Ok(BackupRevision {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    title: row.get(2)?,
+                    content: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            },
+        )?,
+        sort_settings: query_all(
+            conn,
+            "SELECT parent_key, mode, property, descending, updated_at FROM sort_settings ORDER BY parent_key",
+            |row| {
+                let key: i64 = row.get(0)?;
+                Ok(BackupSortSetting {
+                    parent_id: (key != 0).then_some(key),
+                    mode: row.get(1)?,
+                    property: row.get(2)?,
+                    descending: row.get::<_, i64>(3)? != 0,
+                    updated_at: row.get(4)?,
+                })
+            },
+        )?,
+    })
+}
+
+/// Notes ordered parents-first so parent_id references always exist when a row is inserted.
+/// Notes whose parent is missing from the backup (or part of a cycle) come back as roots.
+fn parents_first(notes: &[BackupNote]) -> Vec<(&BackupNote, Option<i64>)> {
+    let mut children: HashMap<Option<i64>, Vec<&BackupNote>> = HashMap::new();
+    let known: HashMap<i64, &BackupNote> = notes.iter().map(|n| (n.id, n)).collect();
+    for note in notes {
+        let parent = note.parent_id.filter(|p| known.contains_key(p));
+        children.entry(parent).or_default().push(note);
+    }
+
+    let mut ordered: Vec<(&BackupNote, Option<i64>)> = Vec::new();
+    let mut queue: VecDeque<(&BackupNote, Option<i64>)> = VecDeque::new();
+    let mut visited = HashSet::new();
+    let mut roots: Vec<&BackupNote> = children.get(&None).cloned().unwrap_or_default();
+    loop {
+        for root in roots.drain(..) {
+            queue.push_back((root, None));
+        }
+        while let Some((note, parent)) = queue.pop_front() {
+            if !visited.insert(note.id) {
+                continue;
+            }
+            ordered.push((note, parent));
+            for child in children.get(&Some(note.id)).into_iter().flatten() {
+                queue.push_back((child, Some(note.id)));
+            }
+        }
+        // Anything left is only reachable through a cycle: detach its first note
+        match notes.iter().find(|n| !visited.contains(&n.id)) {
+            Some(orphan) => roots.push(orphan),
+            None => break,
+        }
+    }
+    ordered
+}
+
+/// A fresh install holds only the welcome note; it counts as empty for a restore.
+/// Saved searches and sort settings are user data too, so any of them makes it non-empty.
+fn is_effectively_empty(conn: &Connection) -> Result<bool, String> {
+    let (count, edited, settings): (i64, i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(parent_id IS NOT NULL OR updated_at != created_at), 0),
+                    (SELECT COUNT(*) FROM saved_searches) + (SELECT COUNT(*) FROM sort_settings)
+             FROM notes",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(settings == 0 && (count == 0 || (count == 1 && edited == 0)))
+}
+
+/// Saved-search filters with `rootId` pointed at the merged copy of the note. A root that
+/// isn't in the backup is cleared rather than left pointing at an unrelated note.
+fn remap_filters(filters: &str, id_map: &HashMap<i64, i64>) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(filters) else {
+        return filters.to_string();
+    };
+    let Some(root) = value.get("rootId").and_then(|v| v.as_str()).map(str::to_string) else {
+        return filters.to_string();
+    };
+    value["rootId"] = root
+        .parse::<i64>()
+        .ok()
+        .and_then(|id| id_map.get(&id))
+        .map_or(serde_json::Value::Null, |id| serde_json::Value::String(id.to_string()));
+    serde_json::to_string(&value).unwrap_or_else(|_| filters.to_string())
+}
+
+fn insert_note(conn: &Connection, note: &BackupNote, id: Option<i64>, parent_id: Option<i64>, order_key: f64) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO notes (id, parent_id, title, content, order_key, is_open, is_pinned, is_markdown_view,
+                            is_deleted, created_at, updated_at, structure_updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            id,
+            parent_id,
+            note.title,
+            note.content,
+            order_key,
+            note.is_open,
+            note.is_pinned,
+            note.is_markdown_view,
+            note.is_deleted,
+            note.created_at,
+            note.updated_at,
+            note.structure_updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn import_backup(conn: &Connection, backup: &Backup, mode: BackupImportMode) -> Result<(), String> {
+    if mode == BackupImportMode::Restore {
+        if !is_effectively_empty(conn)? {
+            return Err("Database is not empty; import in merge mode instead".into());
+        }
+        conn.execute_batch(
+            "DELETE FROM open_state; DELETE FROM note_revisions; DELETE FROM saved_searches;
+             DELETE FROM sort_settings; DELETE FROM notes;",
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    let keep_ids = mode == BackupImportMode::Restore;
+
+    // Merged top-level notes go after the existing roots, keeping their relative order
+    let ordered = parents_first(&backup.notes);
+    let mut root_keys: HashMap<i64, f64> = HashMap::new();
+    if !keep_ids {
+        let mut roots: Vec<&BackupNote> = ordered.iter().filter(|(_, p)| p.is_none()).map(|(n, _)| *n).collect();
+        roots.sort_by(|a, b| a.order_key.partial_cmp(&b.order_key).unwrap_or(std::cmp::Ordering::Equal));
+        let existing = ordering::child_ids(conn, None, &[]).map_err(|e| e.to_string())?;
+        let keys = ordering::keys_between(conn, None, existing.last().copied(), None, roots.len())
+            .map_err(|e| e.to_string())?;
+        root_keys = roots.iter().map(|n| n.id).zip(keys).collect();
+    }
+
+    let mut id_map: HashMap<i64, i64> = HashMap::new();
+    for (note, parent) in ordered {
+        let parent_id = parent.and_then(|p| id_map.get(&p).copied());
+        let order_key = root_keys.get(&note.id).copied().unwrap_or(note.order_key);
+        let new_id = insert_note(conn, note, keep_ids.then_some(note.id), parent_id, order_key)?;
+        id_map.insert(note.id, new_id);
+    }
+
+    for open in &backup.open_state {
+        if let Some(note_id) = id_map.get(&open.note_id) {
+            conn.execute(
+                "INSERT OR REPLACE INTO open_state (note_id, last_opened_at) VALUES (?, ?)",
+                params![note_id, open.last_opened_at],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    for revision in &backup.note_revisions {
+        if let Some(note_id) = id_map.get(&revision.note_id) {
+            conn.execute(
+                "INSERT INTO note_revisions (id, note_id, title, content, created_at) VALUES (?, ?, ?, ?, ?)",
+                params![keep_ids.then_some(revision.id), note_id, revision.title, revision.content, revision.created_at],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    for search in &backup.saved_searches {
+        let filters = if keep_ids {
+            search.filters.clone()
+        } else {
+            remap_filters(&search.filters, &id_map)
+        };
+        conn.execute(
+            "INSERT INTO saved_searches (id, name, query, filters, sort, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                keep_ids.then_some(search.id),
+                search.name,
+                search.query,
+                filters,
+                search.sort,
+                search.created_at,
+                search.updated_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    for setting in &backup.sort_settings {
+        // A merge never overrides the current root-level sort
+        let parent_key = match setting.parent_id {
+            Some(p) => match id_map.get(&p) {
+                Some(&id) => id,
+                None => continue,
+            },
+            None if keep_ids => 0,
+            None => continue,
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO sort_settings (parent_key, mode, property, descending, updated_at) VALUES (?, ?, ?, ?, ?)",
+            params![parent_key, setting.mode, setting.property, setting.descending, setting.updated_at],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Write every app table to `path` as versioned JSON (see `Backup`).
+#[tauri::command]
+pub async fn export_json_backup(app: AppHandle, path: String) -> Result<BackupSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let backup = {
+            let state = app.state::<AppState>();
+            let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+            read_backup(&conn, now)?
+        };
+
+        let json = serde_json::to_string_pretty(&backup).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| e.to_string())?;
+        Ok(backup.summary())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Load a JSON backup from `path`, either restoring it as-is into an empty database
+/// or merging it into the current one with new ids. Runs in one transaction.
+#[tauri::command]
+pub async fn import_json_backup(app: AppHandle, path: String, mode: BackupImportMode) -> Result<BackupSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let json = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let backup: Backup = serde_json::from_str(&json).map_err(|e| format!("Invalid backup file: {}", e))?;
+        if backup.format != BACKUP_FORMAT {
+            return Err("Not an Ultra Memo backup".into());
+        }
+        if backup.version > BACKUP_VERSION {
+            return Err(format!("Unsupported backup version {}", backup.version));
+        }
+
+        let state = app.state::<AppState>();
+        let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        import_backup(&tx, &backup, mode)?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(backup.summary())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::open_in_memory;
+
+    /// Two roots, a child with a revision, a soft-deleted note, open state,
+    /// a saved search scoped to the child and a sort setting on the first root.
+    fn seed(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO notes (id, parent_id, title, content, order_key, is_open, is_pinned, is_markdown_view, is_deleted, created_at, updated_at, structure_updated_at)
+             VALUES (10, NULL, 'Root', 'root body', 1024, 1, 1, 0, 0, 1, 2, 3),
+                    (11, 10, 'Child', 'child [[Root]]', 1024, 0, 0, 1, 0, 4, 5, 6),
+                    (12, 10, 'Gone', 'deleted', 2048, 0, 0, 0, 1, 7, 8, 9),
+                    (20, NULL, 'Second', '', 2048, 0, 0, 0, 0, 10, 11, 12);
+             INSERT INTO open_state (note_id, last_opened_at) VALUES (10, 100);
+             INSERT INTO note_revisions (id, note_id, title, content, created_at) VALUES (5, 11, 'Child', 'old', 50);
+             INSERT INTO saved_searches (id, name, query, filters, sort, created_at, updated_at)
+             VALUES (3, 'Scoped', 'child', '{\"pinnedOnly\":false,\"rootId\":\"11\"}', 'rank', 60, 61);
+             INSERT INTO sort_settings (parent_key, mode, property, descending, updated_at) VALUES (10, 'title', NULL, 1, 70);",
+        )
+        .unwrap();
+    }
+
+    /// Export, then load the JSON back as `import_json_backup` does.
+    fn export(conn: &Connection) -> Backup {
+        let json = serde_json::to_string(&read_backup(conn, 1).unwrap()).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn restore_round_trips() {
+        let source = open_in_memory().unwrap();
+        seed(&source);
+        let backup = export(&source);
+
+        let mut target = open_in_memory().unwrap();
+        let tx = target.transaction().unwrap();
+        import_backup(&tx, &backup, BackupImportMode::Restore).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            serde_json::to_value(read_backup(&target, 1).unwrap()).unwrap(),
+            serde_json::to_value(read_backup(&source, 1).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn restore_rejects_saved_searches_and_sort_settings() {
+        let backup = export(&open_in_memory().unwrap());
+        for sql in [
+            "INSERT INTO saved_searches (name, query, created_at, updated_at) VALUES ('s', 'q', 1, 1)",
+            "INSERT INTO sort_settings (parent_key, mode, updated_at) VALUES (0, 'title', 1)",
+        ] {
+            let conn = open_in_memory().unwrap();
+            conn.execute(sql, []).unwrap();
+            assert!(import_backup(&conn, &backup, BackupImportMode::Restore).is_err());
+        }
+    }
+
+    #[test]
+    fn merge_remaps_ids() {
+        let source = open_in_memory().unwrap();
+        seed(&source);
+        let backup = export(&source);
+
+        let mut target = open_in_memory().unwrap();
+        seed(&target);
+        let tx = target.transaction().unwrap();
+        import_backup(&tx, &backup, BackupImportMode::Merge).unwrap();
+        tx.commit().unwrap();
+
+        let merged = read_backup(&target, 1).unwrap();
+        assert_eq!(merged.notes.len(), 8);
+        let copy = |title: &str| {
+            let ids: Vec<i64> = merged.notes.iter().filter(|n| n.title == title).map(|n| n.id).collect();
+            assert_eq!(ids.len(), 2);
+            ids[1]
+        };
+        let (root, child, gone) = (copy("Root"), copy("Child"), copy("Gone"));
+
+        let note = |id: i64| merged.notes.iter().find(|n| n.id == id).unwrap();
+        assert_eq!(note(child).parent_id, Some(root));
+        assert_eq!(note(child).content, "child [[Root]]");
+        assert!(note(gone).is_deleted);
+        // Merged roots go after the existing ones
+        assert!(note(root).order_key > note(20).order_key);
+
+        assert!(merged.open_state.iter().any(|o| o.note_id == root));
+        assert!(merged.note_revisions.iter().any(|r| r.note_id == child && r.content == "old"));
+        assert!(merged.sort_settings.iter().any(|s| s.parent_id == Some(root) && s.mode == "title"));
+
+        let filters: Vec<serde_json::Value> = merged
+            .saved_searches
+            .iter()
+            .map(|s| serde_json::from_str(&s.filters).unwrap())
+            .collect();
+        assert_eq!(filters[0]["rootId"], "11");
+        assert_eq!(filters[1]["rootId"], child.to_string());
+    }
+}
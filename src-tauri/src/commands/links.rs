@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use regex::Regex;
 use serde::Serialize;
 use tauri::State;
 use crate::AppState;
@@ -19,6 +22,26 @@ fn escape_like(s: &str) -> String {
         .collect()
 }
 
+pub(crate) fn wiki_link_regex() -> Regex {
+    Regex::new(r"\[\[([^\]]+)\]\]").unwrap()
+}
+
+/// Rewrite `[[Old]]` to `[[New]]` for every entry in `renames`.
+/// Keys must be lowercased; titles match case-insensitively, like `resolve_wiki_link`.
+pub(crate) fn rewrite_wiki_links(content: &str, renames: &HashMap<String, String>) -> String {
+    if renames.is_empty() {
+        return content.to_string();
+    }
+    wiki_link_regex()
+        .replace_all(content, |caps: &regex::Captures| {
+            match renames.get(&caps[1].trim().to_lowercase()) {
+                Some(new_title) => format!("[[{}]]", new_title),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
 #[tauri::command]
 pub fn resolve_wiki_link(state: State<'_, AppState>, title: String) -> Result<Option<String>, String> {
     let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
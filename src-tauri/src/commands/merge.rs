@@ -0,0 +1,189 @@
+use std::collections::{HashMap, HashSet};
+
+use tauri::State;
+use crate::AppState;
+use crate::commands::links::rewrite_wiki_links;
+use crate::commands::move_note::{ancestor_chain, move_notes};
+use crate::commands::tree::{tree_node_from_row, TreeNode, TREE_NODE_COLUMNS};
+use rusqlite::params;
+
+/// Merge `ids` into `target_id` in one transaction:
+/// - appends each source's content under a `## Title` heading (selection order, or tree order when `tree_order`)
+/// - re-parents the sources' children to the end of the target
+/// - rewrites `[[Source]]` links everywhere to `[[Target]]`
+/// - soft-deletes the sources
+#[tauri::command]
+pub fn merge_notes(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    target_id: String,
+    tree_order: bool,
+) -> Result<TreeNode, String> {
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let target_int = target_id.parse::<i64>().map_err(|_| "Invalid Target ID")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let mut source_ids: Vec<i64> = Vec::new();
+    for id_str in &ids {
+        let id_int = id_str.parse::<i64>().map_err(|_| format!("Invalid ID: {}", id_str))?;
+        if id_int != target_int && !source_ids.contains(&id_int) {
+            source_ids.push(id_int);
+        }
+    }
+    if source_ids.is_empty() {
+        return Err("Nothing to merge".into());
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let (target_title, mut target_content): (String, String) = tx
+        .query_row(
+            "SELECT title, content FROM notes WHERE id = ? AND is_deleted = 0",
+            [target_int],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // The target may not live inside a source, or re-parenting would create a cycle
+    let mut current: Option<i64> = Some(target_int);
+    while let Some(cur) = current {
+        if source_ids.contains(&cur) {
+            return Err("Cannot merge a note into its own descendant".into());
+        }
+        current = tx
+            .query_row("SELECT parent_id FROM notes WHERE id = ?", [cur], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+    }
+
+    if tree_order {
+        let mut keyed = Vec::new();
+        for id in source_ids {
+            let path: Vec<f64> = ancestor_chain(&tx, id)?.iter().rev().map(|(_, k)| *k).collect();
+            keyed.push((path, id));
+        }
+        keyed.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        source_ids = keyed.into_iter().map(|(_, id)| id).collect();
+    }
+
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut children: Vec<i64> = Vec::new();
+    for &source in &source_ids {
+        let (title, content): (String, String) = tx
+            .query_row(
+                "SELECT title, content FROM notes WHERE id = ? AND is_deleted = 0",
+                [source],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| e.to_string())?;
+
+        if !target_content.is_empty() && !target_content.ends_with('\n') {
+            target_content.push('\n');
+        }
+        if !target_content.is_empty() {
+            target_content.push('\n');
+        }
+        target_content.push_str(&format!("## {}\n\n", title));
+        target_content.push_str(content.trim_end());
+        target_content.push('\n');
+
+        renames.insert(title.trim().to_lowercase(), target_title.clone());
+
+        let mut stmt = tx
+            .prepare("SELECT id FROM notes WHERE parent_id = ? AND is_deleted = 0 ORDER BY order_key")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([source], |row| row.get(0)).map_err(|e| e.to_string())?;
+        for row in rows {
+            let child = row.map_err(|e| e.to_string())?;
+            if !source_ids.contains(&child) {
+                children.push(child);
+            }
+        }
+    }
+
+    // A title some other live note still carries keeps resolving to that note
+    let held: HashSet<String> = {
+        let mut stmt = tx
+            .prepare("SELECT id, title FROM notes WHERE is_deleted = 0")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut held = HashSet::new();
+        for row in rows {
+            let (id, title) = row.map_err(|e| e.to_string())?;
+            if !source_ids.contains(&id) {
+                held.insert(title.trim().to_lowercase());
+            }
+        }
+        held
+    };
+    renames.retain(|title, _| !held.contains(title));
+
+    // Links between merged sources would otherwise point at soft-deleted notes
+    let target_content = rewrite_wiki_links(&target_content, &renames);
+
+    crate::db::record_revision(&tx, target_int, now).map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE notes SET content = ?, updated_at = ? WHERE id = ?",
+        params![target_content, now, target_int],
+    )
+    .map_err(|e| e.to_string())?;
+
+    if !children.is_empty() {
+        move_notes(&tx, &children, Some(target_int), None, None, now)?;
+    }
+
+    // Point incoming links at the target (its own content was rewritten above)
+    let linking: Vec<(i64, String)> = {
+        let mut stmt = tx
+            .prepare("SELECT id, content FROM notes WHERE is_deleted = 0 AND content LIKE '%[[%'")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut linking = Vec::new();
+        for row in rows {
+            linking.push(row.map_err(|e| e.to_string())?);
+        }
+        linking
+    };
+    for (id, content) in linking {
+        if id == target_int || source_ids.contains(&id) {
+            continue;
+        }
+        let rewritten = rewrite_wiki_links(&content, &renames);
+        if rewritten != content {
+            crate::db::record_revision(&tx, id, now).map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE notes SET content = ?, updated_at = ? WHERE id = ?",
+                params![rewritten, now, id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    for &source in &source_ids {
+        tx.execute(
+            "UPDATE notes SET is_deleted = 1, is_open = 0, updated_at = ? WHERE id = ?",
+            params![now, source],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM open_state WHERE note_id = ?", params![source])
+            .map_err(|e| e.to_string())?;
+    }
+
+    let node = tx
+        .query_row(
+            &format!("SELECT {} FROM notes n WHERE n.id = ?", TREE_NODE_COLUMNS),
+            [target_int],
+            tree_node_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(node)
+}
@@ -7,3 +7,17 @@ pub mod trash;
 pub mod export;
 pub mod links;
 pub mod batch;
+pub mod replace;
+pub mod saved_search;
+pub mod search_index;
+pub mod duplicate;
+pub mod merge;
+pub mod split;
+pub mod sort;
+pub mod folder;
+pub mod json_backup;
+pub mod backup;
+pub mod html;
+pub mod opml;
+pub mod enex;
+pub mod archive;
@@ -1,121 +1,193 @@
+use serde::Serialize;
 use tauri::State;
 use crate::AppState;
-use rusqlite::{params, OptionalExtension};
+use crate::commands::sort::sorted_children;
+use crate::ordering;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// A sibling's order key after a move, so the frontend can apply the backend's keys directly.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderKeyUpdate {
+    pub id: String,
+    pub order_key: f64,
+}
+
+/// Current order keys of the live children of `parent_id`, in display order (pinned first,
+/// then the parent's sort setting). A move may renormalize the whole parent, so callers
+/// return every sibling, not just the moved notes.
+pub(crate) fn sibling_keys(conn: &Connection, parent_id: Option<i64>) -> Result<Vec<OrderKeyUpdate>, String> {
+    Ok(sorted_children(conn, parent_id)?
+        .into_iter()
+        .map(|node| OrderKeyUpdate {
+            id: node.id,
+            order_key: node.order_key,
+        })
+        .collect())
+}
+
+/// Walk from `id` up to the root, returning (id, order_key) pairs starting with the note itself.
+pub(crate) fn ancestor_chain(conn: &Connection, id: i64) -> Result<Vec<(i64, f64)>, String> {
+    let mut chain = Vec::new();
+    let mut current = Some(id);
+    while let Some(cur) = current {
+        // parent_id can be NULL (for root nodes), so read as Option<i64>
+        let row: Option<(Option<i64>, f64)> = conn.query_row(
+            "SELECT parent_id, order_key FROM notes WHERE id = ?",
+            [cur],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional().map_err(|e| e.to_string())?;
+
+        match row {
+            Some((parent, order_key)) => {
+                if chain.iter().any(|&(seen, _)| seen == cur) {
+                    return Err("Corrupt tree: parent cycle detected".into());
+                }
+                chain.push((cur, order_key));
+                current = parent;
+            }
+            None if cur == id => return Err(format!("Note not found: {}", id)),
+            None => break,
+        }
+    }
+    Ok(chain)
+}
+
+/// Move `ids` under `new_parent_id` as one contiguous block.
+/// - before_id: place the block immediately BEFORE this sibling
+/// - after_id: place the block immediately AFTER this sibling
+/// - neither: append to the end
+///
+/// The block keeps the notes' current tree order. Notes whose ancestor is also being
+/// moved are skipped, since they travel with that ancestor.
+pub(crate) fn move_notes(
+    conn: &Connection,
+    ids: &[i64],
+    new_parent_id: Option<i64>,
+    before_id: Option<i64>,
+    after_id: Option<i64>,
+    now: i64,
+) -> Result<(), String> {
+    if before_id.is_some() && after_id.is_some() {
+        return Err("Specify either before_id or after_id, not both".into());
+    }
+    if before_id.or(after_id).is_some_and(|target| ids.contains(&target)) {
+        return Err("Cannot place notes next to one of the notes being moved".into());
+    }
+
+    // Resolve each note's path so we can drop nested selections and sort by tree order
+    let mut chains: Vec<(i64, Vec<(i64, f64)>)> = Vec::new();
+    for &id in ids {
+        if !chains.iter().any(|(seen, _)| *seen == id) {
+            chains.push((id, ancestor_chain(conn, id)?));
+        }
+    }
+    let selected: Vec<i64> = chains.iter().map(|(id, _)| *id).collect();
+    chains.retain(|(_, chain)| !chain[1..].iter().any(|(a, _)| selected.contains(a)));
+    chains.sort_by(|(_, a), (_, b)| {
+        let a_path = a.iter().rev().map(|(_, k)| *k);
+        let b_path = b.iter().rev().map(|(_, k)| *k);
+        a_path.partial_cmp(b_path).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let moving: Vec<i64> = chains.iter().map(|(id, _)| *id).collect();
+    if moving.is_empty() {
+        return Ok(());
+    }
+
+    // Cycle check: the new parent must not be a moving note or one of its descendants
+    if let Some(target_parent) = new_parent_id {
+        for (ancestor, _) in ancestor_chain(conn, target_parent)? {
+            if selected.contains(&ancestor) {
+                return Err("Cannot move node into itself or its own descendant".into());
+            }
+        }
+    }
+
+    let siblings = ordering::child_ids(conn, new_parent_id, &moving).map_err(|e| e.to_string())?;
+    let position_of = |target: i64| -> Result<usize, String> {
+        siblings
+            .iter()
+            .position(|&id| id == target)
+            .ok_or_else(|| "Target is not a sibling in the destination".to_string())
+    };
+    let (prev_id, next_id) = match (before_id, after_id) {
+        (Some(before), _) => {
+            let pos = position_of(before)?;
+            (pos.checked_sub(1).map(|p| siblings[p]), Some(before))
+        }
+        (None, Some(after)) => {
+            let pos = position_of(after)?;
+            (Some(after), siblings.get(pos + 1).copied())
+        }
+        (None, None) => (siblings.last().copied(), None),
+    };
+
+    // Only the moved notes get new keys; the parent is renormalized if the gap is exhausted
+    let keys = ordering::keys_between(conn, new_parent_id, prev_id, next_id, moving.len())
+        .map_err(|e| e.to_string())?;
+
+    for (id, key) in moving.iter().zip(keys) {
+        conn.execute(
+            "UPDATE notes SET parent_id = ?, order_key = ?, structure_updated_at = ? WHERE id = ?",
+            params![new_parent_id, key, now, id]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
 
 /// Move a note to a new position.
 /// - new_parent_id: The new parent (null for root)
-/// - after_id: Place the note AFTER this sibling. If None, place at the beginning.
+/// - before_id: Place the note immediately BEFORE this sibling.
+/// - after_id: Place the note immediately AFTER this sibling.
+///
+/// If neither is given, the note is appended to the end.
+/// Returns the order keys of the new parent's children.
 #[tauri::command]
 pub fn move_note(
     state: State<'_, AppState>,
     note_id: String,
     new_parent_id: Option<String>,
-    _before_id: Option<String>, // Ignored in new simple system
-    after_id: Option<String>,   // Place after this node
-) -> Result<(), String> {
+    before_id: Option<String>,
+    after_id: Option<String>,
+) -> Result<Vec<OrderKeyUpdate>, String> {
     let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
     let note_id_int = note_id.parse::<i64>().map_err(|_| "Invalid Note ID")?;
 
-    // Prevent moving into self
-    if let Some(ref pid) = new_parent_id {
-        if pid == &note_id {
-            return Err("Cannot move node into itself".into());
-        }
-    }
-
     let new_parent_id_int: Option<i64> = match new_parent_id.as_ref() {
         Some(s) => Some(s.parse::<i64>().map_err(|_| "Invalid Parent ID")?),
         None => None,
     };
-
-    // Cycle check: ensure new_parent is not a descendant of note_id
-    if let Some(target_parent) = new_parent_id_int {
-        let mut current = target_parent;
-        loop {
-            if current == note_id_int {
-                return Err("Cannot move node into its own descendant".into());
-            }
-            // parent_id can be NULL (for root nodes), so read as Option<i64>
-            let p_res: Option<Option<i64>> = conn.query_row(
-                "SELECT parent_id FROM notes WHERE id = ?",
-                [current],
-                |row| row.get::<_, Option<i64>>(0)
-            ).optional().map_err(|e| e.to_string())?;
-            
-            match p_res {
-                Some(Some(pid)) => current = pid, // Has a parent, continue up the tree
-                Some(None) => break,              // Reached root (parent_id is NULL)
-                None => break,                    // Node not found
-            }
-        }
-    }
-
-    let tx = conn.transaction().map_err(|e| e.to_string())?;
-
-    // Get all siblings of the target parent (excluding the moving node), sorted by order_key
-    let mut siblings: Vec<i64> = {
-        let (sql, params_vec): (&str, Vec<&dyn rusqlite::ToSql>) = match new_parent_id_int {
-            Some(ref pid) => (
-                "SELECT id FROM notes WHERE parent_id = ? AND is_deleted = 0 AND id != ? ORDER BY order_key",
-                vec![pid, &note_id_int],
-            ),
-            None => (
-                "SELECT id FROM notes WHERE parent_id IS NULL AND is_deleted = 0 AND id != ? ORDER BY order_key",
-                vec![&note_id_int],
-            ),
-        };
-
-        let mut stmt = tx.prepare(sql).map_err(|e| e.to_string())?;
-        
-        // Convert Vec<&dyn ToSql> to slice for query_map
-        let rows = stmt.query_map(params_vec.as_slice(), |row| row.get(0))
-            .map_err(|e| e.to_string())?;
-        
-        let mut ids = Vec::new();
-        for row in rows {
-            ids.push(row.map_err(|e| e.to_string())?);
-        }
-        ids
+    let before_id_int = match before_id {
+        Some(s) => Some(s.parse::<i64>().map_err(|_| "Invalid Target ID")?),
+        None => None,
     };
-
-    // Determine insertion index
-    // We insert AT the position of target_id (pushing it down), not after it
-    let insert_index = if let Some(target_id_str) = after_id {
-        let target_id_int = target_id_str.parse::<i64>().map_err(|_| "Invalid Target ID")?;
-        // Find position of target_id and insert AT that position
-        match siblings.iter().position(|&id| id == target_id_int) {
-            Some(pos) => pos, // Insert AT this position (target gets pushed down)
-            None => siblings.len(), // If not found, append to end
-        }
-    } else {
-        siblings.len() // No target means append to end
+    let after_id_int = match after_id {
+        Some(s) => Some(s.parse::<i64>().map_err(|_| "Invalid Target ID")?),
+        None => None,
     };
 
-    // Insert the moving node at the calculated position
-    siblings.insert(insert_index, note_id_int);
-
-    // Update order_key for all siblings: 0, 1000, 2000, 3000, ...
-    // Using 1000 increments to leave room for future inserts without full reindex
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_millis() as i64;
 
-    for (i, sibling_id) in siblings.iter().enumerate() {
-        let new_order = (i as f64) * 1000.0;
-        tx.execute(
-            "UPDATE notes SET order_key = ?, updated_at = ? WHERE id = ?",
-            params![new_order, now, sibling_id]
-        ).map_err(|e| e.to_string())?;
-    }
-
-    // Update parent_id for the moved note
-    tx.execute(
-        "UPDATE notes SET parent_id = ? WHERE id = ?",
-        params![new_parent_id_int, note_id_int]
-    ).map_err(|e| e.to_string())?;
-
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    move_notes(&tx, &[note_id_int], new_parent_id_int, before_id_int, after_id_int, now)?;
+    let keys = sibling_keys(&tx, new_parent_id_int)?;
     tx.commit().map_err(|e| e.to_string())?;
 
-    Ok(())
+    Ok(keys)
+}
+
+/// Display order of the live children of `parent_id`, for re-placing siblings after a local
+/// edit (create, rename, pin) that the parent's sort setting may reorder.
+#[tauri::command]
+pub fn get_sibling_order(state: State<'_, AppState>, parent_id: Option<String>) -> Result<Vec<OrderKeyUpdate>, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let parent_id_int = match parent_id {
+        Some(s) => Some(s.parse::<i64>().map_err(|_| "Invalid Parent ID")?),
+        None => None,
+    };
+    sibling_keys(&conn, parent_id_int)
 }
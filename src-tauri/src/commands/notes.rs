@@ -1,6 +1,7 @@
 use tauri::{State};
 use crate::AppState;
 use crate::commands::tree::TreeNode;
+use crate::ordering;
 use rusqlite::{params, OptionalExtension};
 
 #[derive(serde::Serialize)]
@@ -81,28 +82,20 @@ pub fn create_sibling(state: State<'_, AppState>, selected_id: String) -> Result
         |row| Ok((row.get(0)?, row.get(1)?))
     ).map_err(|e| e.to_string())?;
 
-    let next_order: Option<f64> = match parent_id {
-        Some(pid) => tx.query_row(
-            "SELECT order_key FROM notes WHERE parent_id = ? AND order_key > ? ORDER BY order_key ASC LIMIT 1",
-            params![pid, order_key],
-            |row| row.get(0)
-        ).optional().map_err(|e| e.to_string())?,
-        None => tx.query_row(
-            "SELECT order_key FROM notes WHERE parent_id IS NULL AND order_key > ? ORDER BY order_key ASC LIMIT 1",
-            params![order_key],
-            |row| row.get(0)
-        ).optional().map_err(|e| e.to_string())?,
-    };
+    let next_id: Option<i64> = tx.query_row(
+        "SELECT id FROM notes WHERE parent_id IS ? AND order_key > ? ORDER BY order_key ASC LIMIT 1",
+        params![parent_id, order_key],
+        |row| row.get(0)
+    ).optional().map_err(|e| e.to_string())?;
 
-    let new_order = match next_order {
-        Some(next) => (order_key + next) / 2.0,
-        None => order_key + 1024.0,
-    };
+    // Insert between the selected note and its next sibling
+    let new_order = ordering::keys_between(&tx, parent_id, Some(selected_id_int), next_id, 1)
+        .map_err(|e| e.to_string())?[0];
 
     tx.execute(
-        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at) 
-         VALUES (?, ?, ?, ?, 0, 0, ?, ?)",
-        params![parent_id, "無題", "", new_order, now, now]
+        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at, structure_updated_at) 
+         VALUES (?, ?, ?, ?, 0, 0, ?, ?, ?)",
+        params![parent_id, "無題", "", new_order, now, now, now]
     ).map_err(|e| e.to_string())?;
 
     let new_id = tx.last_insert_rowid();
@@ -120,6 +113,7 @@ pub fn create_sibling(state: State<'_, AppState>, selected_id: String) -> Result
         is_markdown_view: false,
         created_at: now,
         updated_at: now,
+        structure_updated_at: now,
         has_children: false,
     })
 }
@@ -148,12 +142,12 @@ pub fn create_child(state: State<'_, AppState>, parent_id: Option<String>) -> Re
         ).map_err(|e| e.to_string())?,
     };
 
-    let new_order = max_order.unwrap_or(0.0) + 1024.0;
+    let new_order = ordering::key_between(max_order, None);
 
     tx.execute(
-        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at) 
-         VALUES (?, ?, ?, ?, 0, 0, ?, ?)",
-        params![parent_id_int, "無題", "", new_order, now, now]
+        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at, structure_updated_at) 
+         VALUES (?, ?, ?, ?, 0, 0, ?, ?, ?)",
+        params![parent_id_int, "無題", "", new_order, now, now, now]
     ).map_err(|e| e.to_string())?;
 
     let new_id = tx.last_insert_rowid();
@@ -171,6 +165,7 @@ pub fn create_child(state: State<'_, AppState>, parent_id: Option<String>) -> Re
         is_markdown_view: false,
         created_at: now,
         updated_at: now,
+        structure_updated_at: now,
         has_children: false,
     })
 }
@@ -318,13 +313,13 @@ pub fn create_quick_note(state: State<'_, AppState>, title: Option<String>) -> R
         )
         .map_err(|e| e.to_string())?;
 
-    let new_order = min_order.unwrap_or(1024.0) - 1024.0;
+    let new_order = ordering::key_between(None, min_order);
     let note_title = title.unwrap_or_else(|| format_timestamp_title(now));
 
     tx.execute(
-        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at)
-         VALUES (NULL, ?, '', ?, 0, 0, ?, ?)",
-        params![note_title, new_order, now, now],
+        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at, structure_updated_at)
+         VALUES (NULL, ?, '', ?, 0, 0, ?, ?, ?)",
+        params![note_title, new_order, now, now, now],
     )
     .map_err(|e| e.to_string())?;
 
@@ -343,6 +338,7 @@ pub fn create_quick_note(state: State<'_, AppState>, title: Option<String>) -> R
         is_markdown_view: false,
         created_at: now,
         updated_at: now,
+        structure_updated_at: now,
         has_children: false,
     })
 }
@@ -379,12 +375,12 @@ pub fn create_note_with_title(
         ).map_err(|e| e.to_string())?,
     };
 
-    let new_order = max_order.unwrap_or(0.0) + 1024.0;
+    let new_order = ordering::key_between(max_order, None);
 
     tx.execute(
-        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at)
-         VALUES (?, ?, '', ?, 0, 0, ?, ?)",
-        params![parent_id_int, title.trim(), new_order, now, now],
+        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at, structure_updated_at)
+         VALUES (?, ?, '', ?, 0, 0, ?, ?, ?)",
+        params![parent_id_int, title.trim(), new_order, now, now, now],
     )
     .map_err(|e| e.to_string())?;
 
@@ -403,6 +399,7 @@ pub fn create_note_with_title(
         is_markdown_view: false,
         created_at: now,
         updated_at: now,
+        structure_updated_at: now,
         has_children: false,
     })
 }
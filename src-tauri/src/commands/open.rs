@@ -1,5 +1,6 @@
 use tauri::State;
 use crate::AppState;
+use crate::commands::tree::{parse_root_id, SCOPE_CTE, SCOPE_FILTER};
 use rusqlite::params;
 
 #[tauri::command]
@@ -51,18 +52,22 @@ pub fn touch_open(state: State<'_, AppState>, id: String) -> Result<(), String>
     Ok(())
 }
 
+/// Recently opened notes, optionally limited to the hoisted subtree under `root_id`.
 #[tauri::command]
-pub fn get_open_list(state: State<'_, AppState>, limit: i64) -> Result<Vec<String>, String> {
+pub fn get_open_list(state: State<'_, AppState>, limit: i64, root_id: Option<String>) -> Result<Vec<String>, String> {
     let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let root_id_int = parse_root_id(root_id)?;
     
-    let mut stmt = conn.prepare(
-        "SELECT os.note_id FROM open_state os
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE {}
+         SELECT os.note_id FROM open_state os
          INNER JOIN notes n ON n.id = os.note_id
-         WHERE n.is_deleted = 0
-         ORDER BY os.last_opened_at DESC LIMIT ?"
-    ).map_err(|e| e.to_string())?;
+         WHERE n.is_deleted = 0 AND {}
+         ORDER BY os.last_opened_at DESC LIMIT ?2",
+        SCOPE_CTE, SCOPE_FILTER
+    )).map_err(|e| e.to_string())?;
     
-    let ids = stmt.query_map([limit], |row| {
+    let ids = stmt.query_map(params![root_id_int, limit], |row| {
         Ok(row.get::<_, i64>(0)?.to_string())
     }).map_err(|e| e.to_string())?;
     
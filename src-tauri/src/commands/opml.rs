@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::fs;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::AppState;
+use crate::commands::export::{load_note_rows, NoteRow};
+use crate::commands::folder::{children_by_parent, insert_import, ImportEntry};
+use crate::commands::tree::parse_root_id;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpmlImportSummary {
+    pub notes: usize,
+    /// Top-level notes created under the target parent.
+    pub root_ids: Vec<String>,
+}
+
+/// Escape for a double-quoted attribute; newlines and tabs become character references so they survive.
+fn escape_attr(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' => out.push_str("&#9;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn write_outlines(
+    out: &mut String,
+    children: &HashMap<Option<i64>, Vec<&NoteRow>>,
+    parent_id: Option<i64>,
+    depth: usize,
+) {
+    let Some(siblings) = children.get(&parent_id) else {
+        return;
+    };
+    for note in siblings {
+        let indent = "  ".repeat(depth + 2);
+        out.push_str(&format!("{}<outline text=\"{}\"", indent, escape_attr(&note.title)));
+        if !note.content.is_empty() {
+            out.push_str(&format!(" _note=\"{}\"", escape_attr(&note.content)));
+        }
+        if children.contains_key(&Some(note.id)) {
+            out.push_str(">\n");
+            write_outlines(out, children, Some(note.id), depth + 1);
+            out.push_str(&format!("{}</outline>\n", indent));
+        } else {
+            out.push_str("/>\n");
+        }
+    }
+}
+
+/// Export the tree (or the subtree under `root_id`) as OPML 2.0: titles as `text`,
+/// content in the `_note` attribute used by most outliners, siblings in tree order.
+#[tauri::command]
+pub fn export_opml(state: State<'_, AppState>, root_id: Option<String>) -> Result<String, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let root_id_int = parse_root_id(root_id)?;
+    let (notes, start) = load_note_rows(&conn, root_id_int)?;
+
+    let title = match root_id_int {
+        Some(root) => notes.iter().find(|n| n.id == root).map(|n| n.title.as_str()).unwrap_or_default(),
+        None => "Ultra Memo",
+    };
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str(&format!("  <head>\n    <title>{}</title>\n  </head>\n  <body>\n", escape_attr(title)));
+    write_outlines(&mut out, &children_by_parent(&notes), start, 0);
+    out.push_str("  </body>\n</opml>\n");
+    Ok(out)
+}
+
+fn outline_entry(e: &quick_xml::events::BytesStart, now: i64) -> Result<ImportEntry, String> {
+    let mut entry = ImportEntry {
+        title: String::new(),
+        content: String::new(),
+        created_at: now,
+        updated_at: now,
+        is_pinned: false,
+        is_markdown_view: false,
+        children: Vec::new(),
+    };
+    for attr in e.attributes() {
+        let attr = attr.map_err(|e| e.to_string())?;
+        match attr.key.as_ref() {
+            b"text" => entry.title = attr.unescape_value().map_err(|e| e.to_string())?.into_owned(),
+            b"_note" => entry.content = attr.unescape_value().map_err(|e| e.to_string())?.into_owned(),
+            _ => {}
+        }
+    }
+    if entry.title.trim().is_empty() {
+        entry.title = "無題".to_string();
+    }
+    Ok(entry)
+}
+
+/// Parse the `<outline>` elements under `<body>` into import entries.
+fn parse_opml(xml: &str, now: i64) -> Result<Vec<ImportEntry>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut roots: Vec<ImportEntry> = Vec::new();
+    let mut stack: Vec<ImportEntry> = Vec::new();
+    let mut in_body = false;
+    loop {
+        match reader.read_event().map_err(|e| format!("Invalid OPML: {}", e))? {
+            Event::Start(e) if e.name().as_ref() == b"body" => in_body = true,
+            Event::End(e) if e.name().as_ref() == b"body" => in_body = false,
+            Event::Start(e) if in_body && e.name().as_ref() == b"outline" => {
+                stack.push(outline_entry(&e, now)?);
+            }
+            Event::Empty(e) if in_body && e.name().as_ref() == b"outline" => {
+                let entry = outline_entry(&e, now)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(entry),
+                    None => roots.push(entry),
+                }
+            }
+            Event::End(e) if in_body && e.name().as_ref() == b"outline" => {
+                let Some(entry) = stack.pop() else {
+                    return Err("Invalid OPML: unbalanced outline".into());
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(entry),
+                    None => roots.push(entry),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(roots)
+}
+
+/// Import an OPML file, recreating its outline as notes under `target_parent_id`
+/// (appended after existing children). Runs in one transaction.
+#[tauri::command]
+pub async fn import_opml(
+    app: AppHandle,
+    path: String,
+    target_parent_id: Option<String>,
+) -> Result<OpmlImportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let parent_id_int = parse_root_id(target_parent_id).map_err(|_| "Invalid Parent ID".to_string())?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        let xml = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let entries = parse_opml(&xml, now)?;
+
+        let state = app.state::<AppState>();
+        let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut notes = 0;
+        let ids = insert_import(&tx, &entries, parent_id_int, now, &mut || notes += 1)?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(OpmlImportSummary {
+            notes,
+            root_ids: ids.iter().map(|id| id.to_string()).collect(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
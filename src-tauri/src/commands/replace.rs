@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::{Regex, RegexBuilder};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::AppState;
+
+/// A single match inside a note's content.
+/// `line` is 1-based, `column` and `length` are UTF-16 code units (editor positions).
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceMatch {
+    pub index: usize,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub matched: String,
+    pub replacement: String,
+    pub line_text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplacePreviewNote {
+    pub id: String,
+    pub title: String,
+    /// Passed back to `apply_find_replace` to detect edits made after the preview.
+    pub updated_at: i64,
+    pub matches: Vec<ReplaceMatch>,
+}
+
+/// A note shown in the preview, as the caller saw it.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewedNote {
+    pub note_id: String,
+    pub updated_at: i64,
+}
+
+/// A preview match the caller does not want replaced.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExcludedMatch {
+    pub note_id: String,
+    pub index: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceResult {
+    pub changed_note_ids: Vec<String>,
+    pub replaced_count: usize,
+    pub updated_at: i64,
+}
+
+fn build_pattern(find: &str, use_regex: bool, case_sensitive: bool) -> Result<Regex, String> {
+    if find.is_empty() {
+        return Err("Search term is empty".into());
+    }
+    let pattern = if use_regex { find.to_string() } else { regex::escape(find) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .multi_line(true)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Run the replacement over `content`, keeping the matches listed in `skip` untouched.
+/// Returns the rewritten content, every match found, and how many were replaced.
+fn replace_content(
+    re: &Regex,
+    replace: &str,
+    use_regex: bool,
+    content: &str,
+    skip: Option<&HashSet<usize>>,
+) -> (String, Vec<ReplaceMatch>, usize) {
+    let mut output = String::with_capacity(content.len());
+    let mut matches = Vec::new();
+    let mut replaced = 0;
+    let mut last = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut scanned = 0;
+
+    for (index, caps) in re.captures_iter(content).enumerate() {
+        let m = caps.get(0).unwrap();
+
+        let seg = &content[scanned..m.start()];
+        line += seg.matches('\n').count();
+        if let Some(pos) = seg.rfind('\n') {
+            line_start = scanned + pos + 1;
+        }
+        scanned = m.start();
+        let column = content[line_start..m.start()].encode_utf16().count();
+        let line_end = content[line_start..].find('\n').map(|p| line_start + p).unwrap_or(content.len());
+
+        let mut replacement = String::new();
+        if use_regex {
+            caps.expand(replace, &mut replacement);
+        } else {
+            replacement.push_str(replace);
+        }
+
+        output.push_str(&content[last..m.start()]);
+        if skip.is_some_and(|s| s.contains(&index)) {
+            output.push_str(m.as_str());
+        } else {
+            output.push_str(&replacement);
+            replaced += 1;
+        }
+        last = m.end();
+
+        matches.push(ReplaceMatch {
+            index,
+            line,
+            column,
+            length: m.as_str().encode_utf16().count(),
+            matched: m.as_str().to_string(),
+            replacement,
+            line_text: content[line_start..line_end].chars().take(200).collect(),
+        });
+    }
+    output.push_str(&content[last..]);
+
+    (output, matches, replaced)
+}
+
+/// Preview every match of `find` across live notes, grouped by note.
+/// Match indices are stable for the same arguments and are what `apply_find_replace` excludes by.
+#[tauri::command]
+pub fn preview_find_replace(
+    state: State<'_, AppState>,
+    find: String,
+    replace: String,
+    use_regex: bool,
+    case_sensitive: bool,
+) -> Result<Vec<ReplacePreviewNote>, String> {
+    let re = build_pattern(&find, use_regex, case_sensitive)?;
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+
+    let mut stmt = conn
+        .prepare("SELECT id, title, content, updated_at FROM notes WHERE is_deleted = 0 ORDER BY updated_at DESC")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        let (id, title, content, updated_at) = row.map_err(|e| e.to_string())?;
+        let (_, matches, _) = replace_content(&re, &replace, use_regex, &content, None);
+        if !matches.is_empty() {
+            result.push(ReplacePreviewNote {
+                id: id.to_string(),
+                title,
+                updated_at,
+                matches,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Apply a find/replace to the notes of a preview in a single transaction.
+/// Fails without changing anything if any of them was edited or deleted since the preview,
+/// since excluded match indices would no longer line up; preview again in that case.
+/// Each changed note gets a revision snapshot and a bumped `updated_at`.
+#[tauri::command]
+pub fn apply_find_replace(
+    state: State<'_, AppState>,
+    find: String,
+    replace: String,
+    use_regex: bool,
+    case_sensitive: bool,
+    notes: Vec<PreviewedNote>,
+    excluded: Vec<ExcludedMatch>,
+) -> Result<ReplaceResult, String> {
+    let re = build_pattern(&find, use_regex, case_sensitive)?;
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let mut skip: HashMap<i64, HashSet<usize>> = HashMap::new();
+    for ex in excluded {
+        let id_int = ex.note_id.parse::<i64>().map_err(|_| format!("Invalid ID: {}", ex.note_id))?;
+        skip.entry(id_int).or_default().insert(ex.index);
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let mut contents: Vec<(i64, String)> = Vec::new();
+    let mut stale = Vec::new();
+    for note in &notes {
+        let id_int = note.note_id.parse::<i64>().map_err(|_| format!("Invalid ID: {}", note.note_id))?;
+        let row: Option<(String, i64)> = tx
+            .query_row(
+                "SELECT content, updated_at FROM notes WHERE id = ? AND is_deleted = 0",
+                [id_int],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match row {
+            Some((content, updated_at)) if updated_at == note.updated_at => contents.push((id_int, content)),
+            _ => stale.push(note.note_id.clone()),
+        }
+    }
+    if !stale.is_empty() {
+        return Err(format!("Notes changed since the preview: {}", stale.join(", ")));
+    }
+
+    let mut changed_note_ids = Vec::new();
+    let mut replaced_count = 0;
+    for (id, content) in contents {
+        let (new_content, _, replaced) = replace_content(&re, &replace, use_regex, &content, skip.get(&id));
+        if replaced == 0 || new_content == content {
+            continue;
+        }
+        crate::db::record_revision(&tx, id, now).map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE notes SET content = ?, updated_at = ? WHERE id = ?",
+            params![new_content, now, id],
+        )
+        .map_err(|e| e.to_string())?;
+        changed_note_ids.push(id.to_string());
+        replaced_count += replaced;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ReplaceResult {
+        changed_note_ids,
+        replaced_count,
+        updated_at: now,
+    })
+}
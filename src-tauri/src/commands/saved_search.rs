@@ -0,0 +1,157 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::AppState;
+use crate::commands::search::{run_search, with_cancellable_search, SearchFilters, SearchPage, SearchSort};
+use rusqlite::{params, Connection, Row};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    pub filters: SearchFilters,
+    pub sort: SearchSort,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+const SAVED_SEARCH_COLUMNS: &str = "id, name, query, filters, sort, created_at, updated_at";
+
+/// Reads `SAVED_SEARCH_COLUMNS`.
+fn saved_search_from_row(row: &Row) -> rusqlite::Result<SavedSearch> {
+    let filters: String = row.get(3)?;
+    let sort: String = row.get(4)?;
+    Ok(SavedSearch {
+        id: row.get::<_, i64>(0)?.to_string(),
+        name: row.get(1)?,
+        query: row.get(2)?,
+        filters: serde_json::from_str(&filters).unwrap_or_default(),
+        sort: SearchSort::parse(&sort),
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn load_saved_search(conn: &Connection, id: i64) -> Result<SavedSearch, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM saved_searches WHERE id = ?", SAVED_SEARCH_COLUMNS),
+        [id],
+        saved_search_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_saved_search(
+    state: State<'_, AppState>,
+    name: String,
+    query: String,
+    filters: Option<SearchFilters>,
+    sort: Option<SearchSort>,
+) -> Result<SavedSearch, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+    let filters_json = serde_json::to_string(&filters.unwrap_or_default()).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO saved_searches (name, query, filters, sort, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+        params![name.trim(), query, filters_json, sort.unwrap_or_default().as_str(), now, now],
+    )
+    .map_err(|e| e.to_string())?;
+
+    load_saved_search(&conn, conn.last_insert_rowid())
+}
+
+#[tauri::command]
+pub fn list_saved_searches(state: State<'_, AppState>) -> Result<Vec<SavedSearch>, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM saved_searches ORDER BY name COLLATE NOCASE, id",
+            SAVED_SEARCH_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], saved_search_from_row).map_err(|e| e.to_string())?;
+
+    let mut searches = Vec::new();
+    for row in rows {
+        searches.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(searches)
+}
+
+#[tauri::command]
+pub fn update_saved_search(
+    state: State<'_, AppState>,
+    id: String,
+    name: Option<String>,
+    query: Option<String>,
+    filters: Option<SearchFilters>,
+    sort: Option<SearchSort>,
+) -> Result<SavedSearch, String> {
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let id_int = id.parse::<i64>().map_err(|_| "Invalid ID format")?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    if let Some(n) = name {
+        tx.execute("UPDATE saved_searches SET name = ? WHERE id = ?", params![n.trim(), id_int])
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(q) = query {
+        tx.execute("UPDATE saved_searches SET query = ? WHERE id = ?", params![q, id_int])
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(f) = filters {
+        let filters_json = serde_json::to_string(&f).map_err(|e| e.to_string())?;
+        tx.execute("UPDATE saved_searches SET filters = ? WHERE id = ?", params![filters_json, id_int])
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(s) = sort {
+        tx.execute("UPDATE saved_searches SET sort = ? WHERE id = ?", params![s.as_str(), id_int])
+            .map_err(|e| e.to_string())?;
+    }
+    let updated = tx
+        .execute("UPDATE saved_searches SET updated_at = ? WHERE id = ?", params![now, id_int])
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("Saved search not found".into());
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_saved_search(&conn, id_int)
+}
+
+#[tauri::command]
+pub fn delete_saved_search(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let id_int = id.parse::<i64>().map_err(|_| "Invalid ID format")?;
+
+    conn.execute("DELETE FROM saved_searches WHERE id = ?", params![id_int])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Run a saved search; results have the same shape as `search_notes`.
+#[tauri::command]
+pub async fn execute_saved_search(
+    app: AppHandle,
+    id: String,
+    limit: i64,
+    offset: Option<i64>,
+) -> Result<SearchPage, String> {
+    let id_int = id.parse::<i64>().map_err(|_| "Invalid ID format")?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        with_cancellable_search(&state, |conn| {
+            let saved = load_saved_search(conn, id_int)?;
+            run_search(conn, &saved.query, &saved.filters, saved.sort, limit, offset.unwrap_or(0))
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -1,13 +1,47 @@
-use tauri::State;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
 use crate::AppState;
-use rusqlite::params;
+use crate::commands::tree::{parse_root_id, SCOPE_CTE, SCOPE_FILTER};
+use rusqlite::{params_from_iter, Connection, ToSql};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
-#[derive(serde::Serialize)]
+const MAX_MATCHES: usize = 200;
+const MAX_SNIPPETS: usize = 3;
+const SNIPPET_BEFORE: usize = 30;
+const SNIPPET_AFTER: usize = 60;
+
+/// A hit location. `line` is 1-based; `column` and `length` are UTF-16 code units
+/// so the editor can jump to them directly.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRange {
+    field: &'static str,
+    line: usize,
+    column: usize,
+    length: usize,
+}
+
+/// Plain-text excerpt around a content hit; `match_start`/`match_length` are relative to `text`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSnippet {
+    line: usize,
+    text: String,
+    match_start: usize,
+    match_length: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SearchResult {
     id: String,
     title: String,
     snippet: String,
     rank: f64,
+    matches: Vec<MatchRange>,
+    snippets: Vec<SearchSnippet>,
+    hit_count: usize,
 }
 
 /// Quote each whitespace-separated token for safe FTS5 MATCH queries.
@@ -26,30 +60,252 @@ fn fts_query(raw: &str) -> Option<String> {
     Some(terms.join(" "))
 }
 
-#[tauri::command]
-pub fn search_notes(state: State<'_, AppState>, query: String, limit: i64) -> Result<Vec<SearchResult>, String> {
-    let fts = match fts_query(&query) {
+/// Lowercase letters with a canonical diacritic decomposition, and their base letters.
+const DIACRITICS_FROM: &str = "àáâãäåçèéêëìíîïñòóôõöùúûüýÿāăąćĉċčďēĕėęěĝğġģĥĩīĭįĵķĺļľńņňōŏőŕŗřśŝşšţťũūŭůűųŵŷźżžơưǎǐǒǔǖǘǚǜǟǡǧǩǫǭǰǵǹǻȁȃȅȇȉȋȍȏȑȓȕȗșțȟȧȩȫȭȯȱȳ";
+const DIACRITICS_TO: &str = "aaaaaaceeeeiiiinooooouuuuyyaaaccccdeeeeegggghiiiijklllnnnooorrrssssttuuuuuuwyzzzouaiouuuuuaagkoojgnaaaeeiioorruusthaeooooy";
+
+/// Case and diacritic folding as done by FTS5's default `unicode61` tokenizer.
+fn fold_char(c: char) -> char {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    match DIACRITICS_FROM.chars().position(|d| d == lower) {
+        Some(i) => DIACRITICS_TO.chars().nth(i).unwrap_or(lower),
+        None => lower,
+    }
+}
+
+/// Split a line into folded tokens the way `unicode61` does: runs of letters and digits,
+/// everything else separates. Returns (char_start, char_end, token).
+fn tokenize(line: &str) -> Vec<(usize, usize, String)> {
+    let mut tokens = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    let mut count = 0;
+    for (i, c) in line.chars().enumerate() {
+        count = i + 1;
+        if c.is_alphanumeric() {
+            current.get_or_insert_with(|| (i, String::new())).1.push(fold_char(c));
+        } else if let Some((start, token)) = current.take() {
+            tokens.push((start, i, token));
+        }
+    }
+    if let Some((start, token)) = current {
+        tokens.push((start, count, token));
+    }
+    tokens
+}
+
+/// Query terms as token sequences. `fts_query` quotes each term, so FTS treats it as a phrase.
+fn match_terms(raw: &str) -> Vec<Vec<String>> {
+    raw.split_whitespace()
+        .map(|t| tokenize(t).into_iter().map(|(_, _, token)| token).collect::<Vec<_>>())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Find every occurrence of the `terms` phrases in `text`, line by line, in document order.
+/// Matching is on whole tokens, like the FTS query itself.
+/// Returns (line, char_start, char_len) triples; the caller converts to UTF-16.
+fn find_hits(text: &str, terms: &[Vec<String>]) -> Vec<(usize, usize, usize)> {
+    let mut hits = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let tokens = tokenize(line);
+        let mut line_hits: Vec<(usize, usize)> = Vec::new();
+        for term in terms {
+            let mut i = 0;
+            while i + term.len() <= tokens.len() {
+                if tokens[i..i + term.len()].iter().zip(term).all(|(t, q)| &t.2 == q) {
+                    let (start, end) = (tokens[i].0, tokens[i + term.len() - 1].1);
+                    line_hits.push((start, end - start));
+                    i += term.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        line_hits.sort();
+        hits.extend(line_hits.into_iter().map(|(start, len)| (line_idx + 1, start, len)));
+    }
+    hits
+}
+
+fn utf16_len(chars: &[char]) -> usize {
+    chars.iter().map(|c| c.len_utf16()).sum()
+}
+
+/// Build match ranges, snippets and the total hit count for one result row.
+fn locate_matches(title: &str, content: &str, terms: &[Vec<String>]) -> (Vec<MatchRange>, Vec<SearchSnippet>, usize) {
+    let mut matches = Vec::new();
+    let mut snippets = Vec::new();
+    let mut hit_count = 0;
+
+    for (field, text) in [("title", title), ("content", content)] {
+        let lines: Vec<Vec<char>> = text.lines().map(|l| l.chars().collect()).collect();
+        let mut last_snippet_line = 0;
+        for (line, start, len) in find_hits(text, terms) {
+            hit_count += 1;
+            let chars = &lines[line - 1];
+            if matches.len() < MAX_MATCHES {
+                matches.push(MatchRange {
+                    field,
+                    line,
+                    column: utf16_len(&chars[..start]),
+                    length: utf16_len(&chars[start..start + len]),
+                });
+            }
+            if field == "content" && snippets.len() < MAX_SNIPPETS && line != last_snippet_line {
+                let from = start.saturating_sub(SNIPPET_BEFORE);
+                let to = (start + len + SNIPPET_AFTER).min(chars.len());
+                snippets.push(SearchSnippet {
+                    line,
+                    text: chars[from..to].iter().collect(),
+                    match_start: utf16_len(&chars[from..start]),
+                    match_length: utf16_len(&chars[start..start + len]),
+                });
+                last_snippet_line = line;
+            }
+        }
+    }
+
+    (matches, snippets, hit_count)
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchFilters {
+    pub pinned_only: bool,
+    pub updated_after: Option<i64>,
+    pub updated_before: Option<i64>,
+    /// Only search the subtree under this note (hoisting).
+    pub root_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    #[default]
+    Rank,
+    Updated,
+    Created,
+    Title,
+}
+
+impl SearchSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchSort::Rank => "rank",
+            SearchSort::Updated => "updated",
+            SearchSort::Created => "created",
+            SearchSort::Title => "title",
+        }
+    }
+
+    pub fn parse(s: &str) -> SearchSort {
+        match s {
+            "updated" => SearchSort::Updated,
+            "created" => SearchSort::Created,
+            "title" => SearchSort::Title,
+            _ => SearchSort::Rank,
+        }
+    }
+
+    fn order_by(&self) -> &'static str {
+        match self {
+            SearchSort::Rank => "rank",
+            SearchSort::Updated => "n.updated_at DESC",
+            SearchSort::Created => "n.created_at DESC",
+            SearchSort::Title => "n.title COLLATE NOCASE, rank",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPage {
+    results: Vec<SearchResult>,
+    total: i64,
+    next_offset: Option<i64>,
+}
+
+impl SearchPage {
+    fn empty() -> SearchPage {
+        SearchPage {
+            results: Vec::new(),
+            total: 0,
+            next_offset: None,
+        }
+    }
+}
+
+/// Shared FTS query used by `search_notes` and saved searches.
+pub(crate) fn run_search(
+    conn: &Connection,
+    query: &str,
+    filters: &SearchFilters,
+    sort: SearchSort,
+    limit: i64,
+    offset: i64,
+) -> Result<SearchPage, String> {
+    let fts = match fts_query(query) {
         Some(q) => q,
-        None => return Ok(Vec::new()),
+        None => return Ok(SearchPage::empty()),
     };
 
-    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let root_id = parse_root_id(filters.root_id.clone())?;
+
+    // The scope CTE binds ?1, so the root goes first and the remaining `?`s follow it
+    let mut where_sql = format!("WHERE notes_fts MATCH ? AND n.is_deleted = 0 AND {}", SCOPE_FILTER);
+    let mut args: Vec<Box<dyn ToSql>> = vec![Box::new(root_id), Box::new(fts)];
+    if filters.pinned_only {
+        where_sql.push_str(" AND n.is_pinned = 1");
+    }
+    if let Some(after) = filters.updated_after {
+        where_sql.push_str(" AND n.updated_at >= ?");
+        args.push(Box::new(after));
+    }
+    if let Some(before) = filters.updated_before {
+        where_sql.push_str(" AND n.updated_at < ?");
+        args.push(Box::new(before));
+    }
+
+    let total: i64 = conn
+        .query_row(
+            &format!(
+                "WITH RECURSIVE {} SELECT COUNT(*) FROM notes_fts JOIN notes n ON n.id = notes_fts.rowid {}",
+                SCOPE_CTE, where_sql
+            ),
+            params_from_iter(args.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
 
-    let mut stmt = conn.prepare(
-        "SELECT n.id, n.title, snippet(notes_fts, 1, '<b>', '</b>', '...', 64), rank
+    let sql = format!(
+        "WITH RECURSIVE {}
+         SELECT n.id, n.title, snippet(notes_fts, 1, '<b>', '</b>', '...', 64), rank, n.content
          FROM notes_fts
          JOIN notes n ON n.id = notes_fts.rowid
-         WHERE notes_fts MATCH ? AND n.is_deleted = 0
-         ORDER BY rank
-         LIMIT ?"
-    ).map_err(|e| e.to_string())?;
+         {}
+         ORDER BY {} LIMIT ? OFFSET ?",
+        SCOPE_CTE,
+        where_sql,
+        sort.order_by()
+    );
+    args.push(Box::new(limit));
+    args.push(Box::new(offset));
 
-    let rows = stmt.query_map(params![fts, limit], |row| {
+    let terms = match_terms(query);
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map(params_from_iter(args.iter()), |row| {
+        let title: String = row.get(1)?;
+        let content: String = row.get(4)?;
+        let (matches, snippets, hit_count) = locate_matches(&title, &content, &terms);
         Ok(SearchResult {
             id: row.get::<_, i64>(0)?.to_string(),
-            title: row.get(1)?,
+            title,
             snippet: row.get(2)?,
             rank: row.get(3)?,
+            matches,
+            snippets,
+            hit_count,
         })
     }).map_err(|e| e.to_string())?;
 
@@ -58,5 +314,65 @@ pub fn search_notes(state: State<'_, AppState>, query: String, limit: i64) -> Re
         results.push(row.map_err(|e| e.to_string())?);
     }
 
-    Ok(results)
+    let end = offset + results.len() as i64;
+    Ok(SearchPage {
+        next_offset: if end < total { Some(end) } else { None },
+        total,
+        results,
+    })
+}
+
+/// Run `f` on the search connection. Starting another search (or calling `cancel_search`)
+/// bumps the generation, and SQLite's progress handler then interrupts this one.
+pub(crate) fn with_cancellable_search<T>(
+    state: &AppState,
+    f: impl FnOnce(&Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let generation = state.search_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let conn = state.search_db.lock().map_err(|_| "Failed to lock search database")?;
+    if state.search_generation.load(Ordering::SeqCst) != generation {
+        return Err("Search cancelled".into());
+    }
+
+    let current = Arc::clone(&state.search_generation);
+    conn.progress_handler(1000, Some(move || current.load(Ordering::Relaxed) != generation))
+        .map_err(|e| e.to_string())?;
+    let result = f(&conn);
+    conn.progress_handler(0, None::<fn() -> bool>).map_err(|e| e.to_string())?;
+
+    if state.search_generation.load(Ordering::SeqCst) != generation {
+        return Err("Search cancelled".into());
+    }
+    result
+}
+
+/// Async so a newer search or `cancel_search` can run while this one is in flight.
+#[tauri::command]
+pub async fn search_notes(
+    app: AppHandle,
+    query: String,
+    limit: i64,
+    offset: Option<i64>,
+    root_id: Option<String>,
+) -> Result<SearchPage, String> {
+    if fts_query(&query).is_none() {
+        return Ok(SearchPage::empty());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let filters = SearchFilters { root_id, ..SearchFilters::default() };
+        with_cancellable_search(&state, |conn| {
+            run_search(conn, &query, &filters, SearchSort::Rank, limit, offset.unwrap_or(0))
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Abort any in-flight search (e.g. when the quick switcher closes).
+#[tauri::command]
+pub async fn cancel_search(app: AppHandle) -> Result<(), String> {
+    app.state::<AppState>().search_generation.fetch_add(1, Ordering::SeqCst);
+    Ok(())
 }
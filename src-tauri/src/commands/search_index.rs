@@ -0,0 +1,123 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::AppState;
+use rusqlite::{params, Connection};
+
+const REBUILD_BATCH: i64 = 500;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FtsHealth {
+    pub ok: bool,
+    pub message: Option<String>,
+    pub note_count: i64,
+    pub indexed_count: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildProgress {
+    pub processed: i64,
+    pub total: i64,
+}
+
+fn check_index(conn: &Connection) -> Result<FtsHealth, String> {
+    let note_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let indexed_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM notes_fts_docsize", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    // rank = 1 also compares the index against the external content table
+    let check = conn.execute(
+        "INSERT INTO notes_fts(notes_fts, rank) VALUES('integrity-check', 1)",
+        [],
+    );
+
+    let (ok, message) = match check {
+        Ok(_) if note_count == indexed_count => (true, None),
+        Ok(_) => (false, Some(format!("{} notes but {} indexed rows", note_count, indexed_count))),
+        Err(e) => (false, Some(e.to_string())),
+    };
+
+    Ok(FtsHealth {
+        ok,
+        message,
+        note_count,
+        indexed_count,
+    })
+}
+
+fn rebuild_index(app: &AppHandle, conn: &mut Connection) -> Result<i64, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let total: i64 = tx
+        .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    tx.execute("INSERT INTO notes_fts(notes_fts) VALUES('delete-all')", [])
+        .map_err(|e| e.to_string())?;
+
+    let mut processed = 0;
+    let mut last_id = 0;
+    loop {
+        let inserted = tx
+            .execute(
+                "INSERT INTO notes_fts(rowid, title, content)
+                 SELECT id, title, content FROM notes WHERE id > ? ORDER BY id LIMIT ?",
+                params![last_id, REBUILD_BATCH],
+            )
+            .map_err(|e| e.to_string())?;
+        if inserted == 0 {
+            break;
+        }
+        processed += inserted as i64;
+        last_id = tx
+            .query_row("SELECT MAX(rowid) FROM notes_fts_docsize", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        let _ = app.emit("search-index-progress", RebuildProgress { processed, total });
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(processed)
+}
+
+/// Verify the FTS index against the notes table.
+#[tauri::command]
+pub async fn fts_integrity_check(app: AppHandle) -> Result<FtsHealth, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        check_index(&conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Rebuild the FTS index from scratch, emitting `search-index-progress` per batch.
+/// Returns the number of indexed notes.
+#[tauri::command]
+pub async fn rebuild_search_index(app: AppHandle) -> Result<i64, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        rebuild_index(&app, &mut conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Merge FTS index segments.
+#[tauri::command]
+pub async fn optimize_search_index(app: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+        conn.execute("INSERT INTO notes_fts(notes_fts) VALUES('optimize')", [])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
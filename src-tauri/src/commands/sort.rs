@@ -0,0 +1,369 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::AppState;
+use crate::commands::tree::{tree_node_from_row, TreeNode, SIBLING_ORDER, TREE_NODE_COLUMNS};
+use crate::ordering::ORDER_STEP;
+use rusqlite::{params, Connection, OptionalExtension};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildSortMode {
+    #[default]
+    Manual,
+    Title,
+    Created,
+    Updated,
+    Property,
+}
+
+impl ChildSortMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChildSortMode::Manual => "manual",
+            ChildSortMode::Title => "title",
+            ChildSortMode::Created => "created",
+            ChildSortMode::Updated => "updated",
+            ChildSortMode::Property => "property",
+        }
+    }
+
+    pub fn parse(s: &str) -> ChildSortMode {
+        match s {
+            "title" => ChildSortMode::Title,
+            "created" => ChildSortMode::Created,
+            "updated" => ChildSortMode::Updated,
+            "property" => ChildSortMode::Property,
+            _ => ChildSortMode::Manual,
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SortSetting {
+    /// None for the root level.
+    pub parent_id: Option<String>,
+    pub mode: ChildSortMode,
+    /// Frontmatter key used by the `property` mode.
+    pub property: Option<String>,
+    pub descending: bool,
+}
+
+fn parent_key(parent_id: Option<i64>) -> i64 {
+    parent_id.unwrap_or(0)
+}
+
+fn setting_from_row(row: &rusqlite::Row) -> rusqlite::Result<SortSetting> {
+    let key: i64 = row.get(0)?;
+    let mode: String = row.get(1)?;
+    Ok(SortSetting {
+        parent_id: (key != 0).then(|| key.to_string()),
+        mode: ChildSortMode::parse(&mode),
+        property: row.get(2)?,
+        descending: row.get::<_, i64>(3)? != 0,
+    })
+}
+
+/// Sort setting for one parent; `manual` when none is stored.
+pub(crate) fn load_sort_setting(conn: &Connection, parent_id: Option<i64>) -> Result<SortSetting, String> {
+    let stored = conn
+        .query_row(
+            "SELECT parent_key, mode, property, descending FROM sort_settings WHERE parent_key = ?",
+            [parent_key(parent_id)],
+            setting_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(stored.unwrap_or(SortSetting {
+        parent_id: parent_id.map(|id| id.to_string()),
+        mode: ChildSortMode::Manual,
+        property: None,
+        descending: false,
+    }))
+}
+
+fn load_sort_settings(conn: &Connection) -> Result<Vec<SortSetting>, String> {
+    let mut stmt = conn
+        .prepare("SELECT parent_key, mode, property, descending FROM sort_settings WHERE mode != 'manual'")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], setting_from_row).map_err(|e| e.to_string())?;
+    let mut settings = Vec::new();
+    for row in rows {
+        settings.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(settings)
+}
+
+/// Half-width katakana U+FF66..=U+FF9D as full-width.
+const HALFWIDTH_KANA: &str = "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+/// A folded (hiragana) kana combined with a half-width sound mark: か+ﾞ → が, は+ﾟ → ぱ.
+fn with_sound_mark(kana: char, handakuten: bool) -> Option<char> {
+    let (bases, marked) = if handakuten {
+        ("はひふへほ", "ぱぴぷぺぽ")
+    } else {
+        ("かきくけこさしすせそたちつてとはひふへほう", "がぎぐげござじずぜぞだぢづでどばびぶべぼゔ")
+    };
+    let index = bases.chars().position(|c| c == kana)?;
+    marked.chars().nth(index)
+}
+
+/// Fold width and kana so that ｱ/ア/あ (and ｶﾞ/ガ/が) and Ａ/A/a sort together.
+fn collation_chars(s: &str) -> Vec<char> {
+    let mut folded_chars: Vec<char> = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        let folded = match c as u32 {
+            0xFF01..=0xFF5E => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            0x3000 => ' ',
+            0xFF65 => '・',
+            0xFF66..=0xFF9D => HALFWIDTH_KANA.chars().nth((c as u32 - 0xFF66) as usize).unwrap_or(c),
+            0xFF9E | 0xFF9F => {
+                let combined = folded_chars.last().and_then(|&prev| with_sound_mark(prev, c == '\u{FF9F}'));
+                if let (Some(combined), Some(prev)) = (combined, folded_chars.last_mut()) {
+                    *prev = combined;
+                    continue;
+                }
+                c
+            }
+            _ => c,
+        };
+        let folded = match folded as u32 {
+            0x30A1..=0x30F6 => char::from_u32(folded as u32 - 0x60).unwrap_or(folded),
+            _ => folded,
+        };
+        folded_chars.push(folded.to_lowercase().next().unwrap_or(folded));
+    }
+    folded_chars
+}
+
+/// Natural order ("2" < "10") over the folded text.
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let a = collation_chars(a);
+    let b = collation_chars(b);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let start_a = i;
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            let start_b = j;
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+            let digits_a = &a[start_a..i];
+            let digits_b = &b[start_b..j];
+            let digits_a = &digits_a[digits_a.iter().position(|&c| c != '0').unwrap_or(digits_a.len())..];
+            let digits_b = &digits_b[digits_b.iter().position(|&c| c != '0').unwrap_or(digits_b.len())..];
+            let ord = digits_a.len().cmp(&digits_b.len()).then_with(|| digits_a.cmp(digits_b));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        } else {
+            let ord = a[i].cmp(&b[j]);
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+/// Sort one parent's children by `setting`. Pinned notes stay first; ties fall back to manual order.
+pub(crate) fn sort_siblings<T>(
+    conn: &Connection,
+    setting: &SortSetting,
+    items: &mut [T],
+    node: impl Fn(&T) -> &TreeNode,
+) -> Result<(), String> {
+    if setting.mode == ChildSortMode::Manual {
+        return Ok(());
+    }
+
+    let mut properties: HashMap<String, Option<String>> = HashMap::new();
+    if setting.mode == ChildSortMode::Property {
+        let key = setting.property.as_deref().unwrap_or_default();
+        let mut stmt = conn
+            .prepare("SELECT content FROM notes WHERE id = ?")
+            .map_err(|e| e.to_string())?;
+        for item in items.iter() {
+            let id = &node(item).id;
+            let content: String = stmt
+                .query_row([id.parse::<i64>().unwrap_or_default()], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            properties.insert(id.clone(), crate::frontmatter::get(&content, key));
+        }
+    }
+
+    items.sort_by(|a, b| {
+        let (a, b) = (node(a), node(b));
+        b.is_pinned.cmp(&a.is_pinned)
+            .then_with(|| {
+                let ord = match setting.mode {
+                    ChildSortMode::Title => natural_cmp(&a.title, &b.title),
+                    ChildSortMode::Created => a.created_at.cmp(&b.created_at),
+                    ChildSortMode::Updated => a.updated_at.cmp(&b.updated_at),
+                    ChildSortMode::Property => {
+                        // Notes without the property go last in either direction
+                        match (&properties[&a.id], &properties[&b.id]) {
+                            (Some(x), Some(y)) => {
+                                let ord = natural_cmp(x, y);
+                                return if setting.descending { ord.reverse() } else { ord };
+                            }
+                            (Some(_), None) => return Ordering::Less,
+                            (None, Some(_)) => return Ordering::Greater,
+                            (None, None) => Ordering::Equal,
+                        }
+                    }
+                    ChildSortMode::Manual => Ordering::Equal,
+                };
+                if setting.descending { ord.reverse() } else { ord }
+            })
+            .then_with(|| a.order_key.partial_cmp(&b.order_key).unwrap_or(Ordering::Equal))
+            .then_with(|| a.id.parse::<i64>().unwrap_or_default().cmp(&b.id.parse::<i64>().unwrap_or_default()))
+    });
+    Ok(())
+}
+
+/// Re-sort every run of siblings in `items` whose parent has a computed sort.
+/// `items` must already be grouped by parent, as the snapshot and subtree queries return them.
+pub(crate) fn apply_sort_settings<T>(
+    conn: &Connection,
+    items: &mut [T],
+    node: impl Fn(&T) -> &TreeNode + Copy,
+) -> Result<(), String> {
+    let settings = load_sort_settings(conn)?;
+    if settings.is_empty() {
+        return Ok(());
+    }
+    let by_parent: HashMap<Option<String>, SortSetting> =
+        settings.into_iter().map(|s| (s.parent_id.clone(), s)).collect();
+
+    let mut start = 0;
+    while start < items.len() {
+        let parent = node(&items[start]).parent_id.clone();
+        let mut end = start + 1;
+        while end < items.len() && node(&items[end]).parent_id == parent {
+            end += 1;
+        }
+        if let Some(setting) = by_parent.get(&parent) {
+            sort_siblings(conn, setting, &mut items[start..end], node)?;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// Live children of `parent_id` in their effective display order.
+pub(crate) fn sorted_children(conn: &Connection, parent_id: Option<i64>) -> Result<Vec<TreeNode>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM notes n WHERE n.parent_id IS ? AND n.is_deleted = 0 ORDER BY {}",
+            TREE_NODE_COLUMNS, SIBLING_ORDER
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([parent_id], tree_node_from_row).map_err(|e| e.to_string())?;
+    let mut nodes = Vec::new();
+    for row in rows {
+        nodes.push(row.map_err(|e| e.to_string())?);
+    }
+    sort_siblings(conn, &load_sort_setting(conn, parent_id)?, &mut nodes, |n| n)?;
+    Ok(nodes)
+}
+
+fn parse_parent_id(parent_id: Option<String>) -> Result<Option<i64>, String> {
+    match parent_id {
+        Some(s) => Ok(Some(s.parse::<i64>().map_err(|_| "Invalid Parent ID")?)),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub fn list_sort_settings(state: State<'_, AppState>) -> Result<Vec<SortSetting>, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    load_sort_settings(&conn)
+}
+
+#[tauri::command]
+pub fn get_sort_setting(state: State<'_, AppState>, parent_id: Option<String>) -> Result<SortSetting, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    load_sort_setting(&conn, parse_parent_id(parent_id)?)
+}
+
+/// Set how `parent_id`'s children (root when None) are ordered. `manual` clears the setting.
+#[tauri::command]
+pub fn set_sort_setting(
+    state: State<'_, AppState>,
+    parent_id: Option<String>,
+    mode: ChildSortMode,
+    property: Option<String>,
+    descending: Option<bool>,
+) -> Result<SortSetting, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let parent_id_int = parse_parent_id(parent_id)?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+
+    let property = property.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if mode == ChildSortMode::Property && property.is_none() {
+        return Err("Property sort needs a property name".into());
+    }
+
+    if mode == ChildSortMode::Manual {
+        conn.execute("DELETE FROM sort_settings WHERE parent_key = ?", [parent_key(parent_id_int)])
+            .map_err(|e| e.to_string())?;
+    } else {
+        conn.execute(
+            "INSERT INTO sort_settings (parent_key, mode, property, descending, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(parent_key) DO UPDATE SET
+                mode = excluded.mode, property = excluded.property,
+                descending = excluded.descending, updated_at = excluded.updated_at",
+            params![parent_key(parent_id_int), mode.as_str(), property, descending.unwrap_or(false), now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    load_sort_setting(&conn, parent_id_int)
+}
+
+/// Write the current computed order of `parent_id`'s children into `order_key`
+/// and switch the parent back to manual sorting.
+#[tauri::command]
+pub fn freeze_sort(state: State<'_, AppState>, parent_id: Option<String>) -> Result<(), String> {
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let parent_id_int = parse_parent_id(parent_id)?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let mut ids: Vec<i64> = sorted_children(&tx, parent_id_int)?
+        .iter()
+        .map(|child| child.id.parse::<i64>().unwrap_or_default())
+        .collect();
+    // Soft-deleted children go after the live ones so a restore doesn't land mid-order
+    {
+        let mut stmt = tx
+            .prepare("SELECT id FROM notes WHERE parent_id IS ? AND is_deleted = 1 ORDER BY order_key, id")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([parent_id_int], |row| row.get(0)).map_err(|e| e.to_string())?;
+        for row in rows {
+            ids.push(row.map_err(|e| e.to_string())?);
+        }
+    }
+    for (i, id) in ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE notes SET order_key = ?, structure_updated_at = ? WHERE id = ?",
+            params![(i as f64 + 1.0) * ORDER_STEP, now, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute("DELETE FROM sort_settings WHERE parent_key = ?", [parent_key(parent_id_int)])
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use tauri::State;
+use crate::AppState;
+use crate::commands::tree::{tree_node_from_row, TreeNode, TREE_NODE_COLUMNS};
+use crate::naming::unique_name;
+use crate::ordering;
+use rusqlite::params;
+
+struct Section {
+    title: String,
+    body: String,
+}
+
+/// ATX heading level and text (`## Title ##` -> (2, "Title")).
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') && !rest.starts_with('\t') {
+        return None;
+    }
+    // A closing `#` run only counts when whitespace precedes it (`## C#` keeps its `#`)
+    let mut text = rest.trim();
+    let without_closing = text.trim_end_matches('#');
+    if without_closing.is_empty() {
+        text = without_closing;
+    } else if without_closing.ends_with([' ', '\t']) {
+        text = without_closing.trim_end();
+    }
+    Some((level, text.to_string()))
+}
+
+/// Split `content` at headings of exactly `level`. A section runs until the next heading
+/// at `level` or above; everything else (preamble, higher-level headings) stays in the parent.
+/// Fenced code blocks are never split.
+fn split_sections(content: &str, level: usize) -> (String, Vec<Section>) {
+    let mut kept = String::new();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_section = false;
+    let mut fence: Option<&str> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some((l, text)) = parse_heading(line) {
+            if l == level {
+                sections.push(Section { title: text, body: String::new() });
+                in_section = true;
+                continue;
+            }
+            if l < level {
+                in_section = false;
+            }
+        }
+
+        let target = match (in_section, sections.last_mut()) {
+            (true, Some(section)) => &mut section.body,
+            _ => &mut kept,
+        };
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    for section in &mut sections {
+        section.body = section.body.trim_matches('\n').to_string();
+        if section.title.is_empty() {
+            section.title = "無題".to_string();
+        }
+    }
+    (kept.trim_end().to_string(), sections)
+}
+
+/// Break a note into child notes, one per heading at `level` (1-6).
+/// The parent keeps its other content plus a list of `[[links]]` to the new children;
+/// child titles get a ` (2)`… suffix where needed so each link resolves to exactly one note.
+/// Runs in one transaction, so a failure leaves the note untouched.
+#[tauri::command]
+pub fn split_note_by_headings(state: State<'_, AppState>, id: String, level: usize) -> Result<Vec<TreeNode>, String> {
+    if !(1..=6).contains(&level) {
+        return Err("Heading level must be between 1 and 6".into());
+    }
+    let mut conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let id_int = id.parse::<i64>().map_err(|_| "Invalid ID format")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let content: String = tx
+        .query_row(
+            "SELECT content FROM notes WHERE id = ? AND is_deleted = 0",
+            [id_int],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let (kept, mut sections) = split_sections(&content, level);
+    if sections.is_empty() {
+        return Err(format!("No level {} headings to split on", level));
+    }
+
+    // Links resolve by title, so a child may share it with no other live note
+    let mut used = HashSet::new();
+    {
+        let mut stmt = tx
+            .prepare("SELECT title FROM notes WHERE is_deleted = 0")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+        for row in rows {
+            used.insert(row.map_err(|e| e.to_string())?.to_lowercase());
+        }
+    }
+    for section in &mut sections {
+        section.title = unique_name(&mut used, std::mem::take(&mut section.title));
+    }
+
+    // New children go after any existing ones, in document order
+    let existing = ordering::child_ids(&tx, Some(id_int), &[]).map_err(|e| e.to_string())?;
+    let keys = ordering::keys_between(&tx, Some(id_int), existing.last().copied(), None, sections.len())
+        .map_err(|e| e.to_string())?;
+
+    let mut new_ids = Vec::new();
+    for (section, key) in sections.iter().zip(keys) {
+        tx.execute(
+            "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_deleted, created_at, updated_at, structure_updated_at)
+             VALUES (?, ?, ?, ?, 0, 0, ?, ?, ?)",
+            params![id_int, section.title, section.body, key, now, now, now],
+        )
+        .map_err(|e| e.to_string())?;
+        new_ids.push(tx.last_insert_rowid());
+    }
+
+    let mut parent_content = kept;
+    if !parent_content.is_empty() {
+        parent_content.push_str("\n\n");
+    }
+    for section in &sections {
+        parent_content.push_str(&format!("- [[{}]]\n", section.title));
+    }
+
+    crate::db::record_revision(&tx, id_int, now).map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE notes SET content = ?, updated_at = ? WHERE id = ?",
+        params![parent_content, now, id_int],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut nodes = Vec::new();
+    {
+        let mut stmt = tx
+            .prepare(&format!("SELECT {} FROM notes n WHERE n.id = ?", TREE_NODE_COLUMNS))
+            .map_err(|e| e.to_string())?;
+        for new_id in new_ids {
+            nodes.push(stmt.query_row([new_id], tree_node_from_row).map_err(|e| e.to_string())?);
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closing_hashes_need_leading_whitespace() {
+        assert_eq!(parse_heading("## Title ##"), Some((2, "Title".to_string())));
+        assert_eq!(parse_heading("## C#"), Some((2, "C#".to_string())));
+        assert_eq!(parse_heading("# F# and C# #"), Some((1, "F# and C#".to_string())));
+        assert_eq!(parse_heading("### ###"), Some((3, String::new())));
+        assert_eq!(parse_heading("#hashtag"), None);
+    }
+}
@@ -61,9 +61,10 @@ pub fn restore_note(state: State<'_, AppState>, id: String) -> Result<(), String
             .map(|n: i64| n > 0)
             .unwrap_or(false);
         if !parent_alive {
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
             conn.execute(
-                "UPDATE notes SET parent_id = NULL WHERE id = ?",
-                params![id_int],
+                "UPDATE notes SET parent_id = NULL, structure_updated_at = ? WHERE id = ?",
+                params![now, id_int],
             )
             .map_err(|e| e.to_string())?;
         }
@@ -1,6 +1,10 @@
+use std::collections::{HashMap, HashSet};
+
 use serde::Serialize;
 use tauri::{State};
 use crate::AppState;
+use crate::commands::sort::{apply_sort_settings, load_sort_setting, sort_siblings, ChildSortMode};
+use rusqlite::{params, Connection, OptionalExtension, Row};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,51 +20,430 @@ pub struct TreeNode {
     pub is_markdown_view: bool,
     pub created_at: i64,
     pub updated_at: i64,
+    /// Last move/reorder; `updated_at` only tracks content edits.
+    pub structure_updated_at: i64,
     pub has_children: bool,
 }
 
-#[tauri::command]
-pub fn get_tree_snapshot(state: State<'_, AppState>) -> Result<Vec<TreeNode>, String> {
-    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
-    
-    // Fetch nodes with has_children check
-    let mut stmt = conn.prepare(
-        "SELECT 
+/// Node columns shared by `TREE_NODE_COLUMNS` and `LAZY_TREE_NODE_COLUMNS`.
+macro_rules! node_columns {
+    () => {
+        "
             n.id, n.parent_id, n.title,
             CASE WHEN length(n.content) > 0 THEN substr(trim(n.content), 1, 80) ELSE '' END,
             length(n.content),
-            n.order_key, n.is_open, n.is_pinned, n.is_markdown_view, n.created_at, n.updated_at,
-            EXISTS(SELECT 1 FROM notes c WHERE c.parent_id = n.id AND c.is_deleted = 0) as has_children
+            n.order_key, n.is_open, n.is_pinned, n.is_markdown_view, n.created_at, n.updated_at, n.structure_updated_at,"
+    };
+}
+
+/// Columns selected for a `TreeNode`, in the order `tree_node_from_row` expects.
+pub(crate) const TREE_NODE_COLUMNS: &str = concat!(
+    node_columns!(),
+    "
+            EXISTS(SELECT 1 FROM notes c WHERE c.parent_id = n.id AND c.is_deleted = 0) as has_children"
+);
+
+/// Like `TREE_NODE_COLUMNS`, but the last column is the live child count;
+/// `has_children` is derived from it.
+const LAZY_TREE_NODE_COLUMNS: &str = concat!(
+    node_columns!(),
+    "
+            (SELECT COUNT(*) FROM notes c WHERE c.parent_id = n.id AND c.is_deleted = 0) as child_count"
+);
+
+/// Sibling order (pinned first, then manual order) shared by the full snapshot and lazy loading.
+pub(crate) const SIBLING_ORDER: &str = "n.is_pinned DESC, n.order_key, n.id";
+
+/// Recursive CTE `scope(id)` over the live subtree rooted at `?1` (root included), for hoisting.
+/// Pair with `SCOPE_FILTER`; when `?1` is NULL the filter passes every note.
+pub(crate) const SCOPE_CTE: &str = "scope(id) AS (
+            SELECT id FROM notes WHERE id = ?1 AND is_deleted = 0
+            UNION ALL
+            SELECT c.id FROM notes c JOIN scope ON c.parent_id = scope.id WHERE c.is_deleted = 0
+         )";
+
+pub(crate) const SCOPE_FILTER: &str = "(?1 IS NULL OR n.id IN (SELECT id FROM scope))";
+
+pub(crate) fn parse_root_id(root_id: Option<String>) -> Result<Option<i64>, String> {
+    match root_id {
+        Some(s) => Ok(Some(s.parse::<i64>().map_err(|_| "Invalid Root ID")?)),
+        None => Ok(None),
+    }
+}
+
+/// Ids of the live subtree under `root_id` (root included), or None when not hoisted.
+fn scope_ids(conn: &Connection, root_id: Option<i64>) -> Result<Option<HashSet<i64>>, String> {
+    let Some(root) = root_id else {
+        return Ok(None);
+    };
+    let mut stmt = conn
+        .prepare(&format!("WITH RECURSIVE {} SELECT id FROM scope", SCOPE_CTE))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([root], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let mut ids = HashSet::new();
+    for row in rows {
+        ids.insert(row.map_err(|e| e.to_string())?);
+    }
+    Ok(Some(ids))
+}
+
+/// A hoisted root is top-level in its scoped view, whatever its real parent.
+fn detach_root(node: &mut TreeNode, root_id: Option<i64>) {
+    if root_id.is_some_and(|root| node.id == root.to_string()) {
+        node.parent_id = None;
+    }
+}
+
+pub(crate) fn tree_node_from_row(row: &Row) -> rusqlite::Result<TreeNode> {
+    Ok(TreeNode {
+        id: row.get::<_, i64>(0)?.to_string(),
+        parent_id: row.get::<_, Option<i64>>(1)?.map(|id| id.to_string()),
+        title: row.get(2)?,
+        content_preview: row.get(3)?,
+        content_length: row.get::<_, i64>(4)? as usize,
+        order_key: row.get(5)?,
+        is_open: row.get::<_, i64>(6)? != 0,
+        is_pinned: row.get::<_, i64>(7)? != 0,
+        is_markdown_view: row.get::<_, i64>(8)? != 0,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+        structure_updated_at: row.get(11)?,
+        has_children: row.get::<_, i64>(12)? != 0,
+    })
+}
+
+/// Live notes (or the subtree under `root_id`), grouped by parent in display order:
+/// pinned first, then each parent's sort setting.
+pub(crate) fn load_tree_snapshot(conn: &Connection, root_id: Option<i64>) -> Result<Vec<TreeNode>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE {}
+         SELECT {}
          FROM notes n
-         WHERE n.is_deleted = 0
-         ORDER BY n.is_pinned DESC, n.parent_id, n.order_key"
-    ).map_err(|e| e.to_string())?;
+         WHERE n.is_deleted = 0 AND {}
+         ORDER BY n.parent_id, {}",
+        SCOPE_CTE, TREE_NODE_COLUMNS, SCOPE_FILTER, SIBLING_ORDER
+    )).map_err(|e| e.to_string())?;
 
-    let nodes = stmt.query_map([], |row| {
-        Ok(TreeNode {
-            id: row.get::<_, i64>(0)?.to_string(),
-            parent_id: row.get::<_, Option<i64>>(1)?.map(|id| id.to_string()),
-            title: row.get(2)?,
-            content_preview: row.get(3)?,
-            content_length: row.get::<_, i64>(4)? as usize,
-            order_key: row.get(5)?,
-            is_open: row.get::<_, i64>(6)? != 0,
-            is_pinned: row.get::<_, i64>(7)? != 0,
-            is_markdown_view: row.get::<_, i64>(8)? != 0,
-            created_at: row.get(9)?,
-            updated_at: row.get(10)?,
-            has_children: row.get::<_, i64>(11)? != 0,
-        })
-    }).map_err(|e| e.to_string())?;
+    let nodes = stmt.query_map([root_id], tree_node_from_row).map_err(|e| e.to_string())?;
 
     let mut result = Vec::new();
     for node in nodes {
+        let mut node = node.map_err(|e| e.to_string())?;
+        detach_root(&mut node, root_id);
+        result.push(node);
+    }
+    apply_sort_settings(conn, &mut result, |n| n)?;
+
+    Ok(result)
+}
+
+/// Every live note, or only the subtree under `root_id` (root included) when hoisted.
+#[tauri::command]
+pub fn get_tree_snapshot(state: State<'_, AppState>, root_id: Option<String>) -> Result<Vec<TreeNode>, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    load_tree_snapshot(&conn, parse_root_id(root_id)?)
+}
+
+/// A `TreeNode` plus its live child count, for lazily loaded branches.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LazyTreeNode {
+    #[serde(flatten)]
+    pub node: TreeNode,
+    pub child_count: i64,
+}
+
+/// Reads `LAZY_TREE_NODE_COLUMNS`; a non-zero count maps to `has_children`.
+fn lazy_tree_node_from_row(row: &Row) -> rusqlite::Result<LazyTreeNode> {
+    Ok(LazyTreeNode {
+        node: tree_node_from_row(row)?,
+        child_count: row.get(12)?,
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildrenPage {
+    pub nodes: Vec<LazyTreeNode>,
+    /// Pass as `cursor` to fetch the next page; None when exhausted.
+    pub next_cursor: Option<String>,
+}
+
+/// One page of a parent's live children, in the same order as `get_tree_snapshot`
+/// (including the parent's sort setting).
+/// `cursor` is the id of the last node of the previous page.
+/// When hoisted to `root_id`, the top level is the root itself and `parent_id` must lie inside it.
+#[tauri::command]
+pub fn get_children(
+    state: State<'_, AppState>,
+    parent_id: Option<String>,
+    cursor: Option<String>,
+    limit: i64,
+    root_id: Option<String>,
+) -> Result<ChildrenPage, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let parent_id_int = match parent_id.as_ref() {
+        Some(id_str) => Some(id_str.parse::<i64>().map_err(|_| "Invalid Parent ID")?),
+        None => None,
+    };
+    let root_id_int = parse_root_id(root_id)?;
+
+    if let Some(root) = root_id_int {
+        match parent_id_int {
+            None => {
+                let mut nodes = Vec::new();
+                if cursor.is_none() && limit > 0 {
+                    let node = conn
+                        .query_row(
+                            &format!("SELECT {} FROM notes n WHERE n.id = ? AND n.is_deleted = 0", LAZY_TREE_NODE_COLUMNS),
+                            [root],
+                            lazy_tree_node_from_row,
+                        )
+                        .optional()
+                        .map_err(|e| e.to_string())?;
+                    if let Some(mut node) = node {
+                        detach_root(&mut node.node, root_id_int);
+                        nodes.push(node);
+                    }
+                }
+                return Ok(ChildrenPage { nodes, next_cursor: None });
+            }
+            Some(parent) => {
+                let in_scope = scope_ids(&conn, root_id_int)?.is_some_and(|ids| ids.contains(&parent));
+                if !in_scope {
+                    return Err("Parent is outside the hoisted subtree".into());
+                }
+            }
+        }
+    }
+
+    // Computed sorts can't use the keyset below: sort all children and slice after the cursor
+    let setting = load_sort_setting(&conn, parent_id_int)?;
+    if setting.mode != ChildSortMode::Manual {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM notes n WHERE n.parent_id IS ? AND n.is_deleted = 0 ORDER BY {}",
+            LAZY_TREE_NODE_COLUMNS, SIBLING_ORDER
+        )).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([parent_id_int], lazy_tree_node_from_row).map_err(|e| e.to_string())?;
+        let mut all = Vec::new();
+        for node in rows {
+            all.push(node.map_err(|e| e.to_string())?);
+        }
+        sort_siblings(&conn, &setting, &mut all, |n| &n.node)?;
+
+        let start = match cursor {
+            Some(c) => all.iter().position(|n| n.node.id == c).ok_or("Invalid cursor")? + 1,
+            None => 0,
+        };
+        let mut nodes: Vec<LazyTreeNode> = all.into_iter().skip(start).take(limit.max(0) as usize + 1).collect();
+        let next_cursor = if nodes.len() as i64 > limit {
+            nodes.truncate(limit.max(0) as usize);
+            nodes.last().map(|n| n.node.id.clone())
+        } else {
+            None
+        };
+        return Ok(ChildrenPage { nodes, next_cursor });
+    }
+
+    // Keyset position of the cursor node: (is_pinned, order_key, id)
+    let after: Option<(i64, f64, i64)> = match cursor {
+        Some(c) => {
+            let cursor_id = c.parse::<i64>().map_err(|_| "Invalid cursor")?;
+            Some(
+                conn.query_row(
+                    "SELECT is_pinned, order_key, id FROM notes WHERE id = ?",
+                    [cursor_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .map_err(|_| "Invalid cursor")?,
+            )
+        }
+        None => None,
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+         FROM notes n
+         WHERE n.parent_id IS ?1 AND n.is_deleted = 0
+           AND (?2 IS NULL
+                OR n.is_pinned < ?3
+                OR (n.is_pinned = ?3 AND (n.order_key > ?4 OR (n.order_key = ?4 AND n.id > ?2))))
+         ORDER BY {}
+         LIMIT ?5",
+        LAZY_TREE_NODE_COLUMNS, SIBLING_ORDER
+    )).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map(
+        params![
+            parent_id_int,
+            after.map(|a| a.2),
+            after.map(|a| a.0),
+            after.map(|a| a.1),
+            limit + 1
+        ],
+        lazy_tree_node_from_row,
+    ).map_err(|e| e.to_string())?;
+
+    let mut nodes = Vec::new();
+    for node in rows {
+        nodes.push(node.map_err(|e| e.to_string())?);
+    }
+
+    let next_cursor = if nodes.len() as i64 > limit {
+        nodes.truncate(limit.max(0) as usize);
+        nodes.last().map(|n| n.node.id.clone())
+    } else {
+        None
+    };
+
+    Ok(ChildrenPage { nodes, next_cursor })
+}
+
+/// The live subtree under `root_id` (root included), down to `max_depth` levels below it.
+#[tauri::command]
+pub fn get_subtree(
+    state: State<'_, AppState>,
+    root_id: String,
+    max_depth: Option<i64>,
+) -> Result<Vec<LazyTreeNode>, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let root_id_int = root_id.parse::<i64>().map_err(|_| "Invalid note ID")?;
+
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE sub(id, depth) AS (
+            SELECT id, 0 FROM notes WHERE id = ?1 AND is_deleted = 0
+            UNION ALL
+            SELECT c.id, sub.depth + 1 FROM notes c
+            JOIN sub ON c.parent_id = sub.id
+            WHERE c.is_deleted = 0 AND (?2 IS NULL OR sub.depth < ?2)
+         )
+         SELECT {}
+         FROM sub JOIN notes n ON n.id = sub.id
+         ORDER BY sub.depth, n.parent_id, {}",
+        LAZY_TREE_NODE_COLUMNS, SIBLING_ORDER
+    )).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map(params![root_id_int, max_depth], lazy_tree_node_from_row)
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for node in rows {
         result.push(node.map_err(|e| e.to_string())?);
     }
+    apply_sort_settings(&conn, &mut result, |n| &n.node)?;
 
     Ok(result)
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeChanges {
+    /// Pass this back as `since_seq` on the next call.
+    pub seq: i64,
+    /// The change log no longer covers `since_seq`; refetch the full snapshot.
+    pub reset: bool,
+    pub inserted: Vec<TreeNode>,
+    pub updated: Vec<TreeNode>,
+    pub moved: Vec<TreeNode>,
+    pub deleted_ids: Vec<String>,
+}
+
+/// Latest change sequence; read it before `get_tree_snapshot` to start incremental sync.
+#[tauri::command]
+pub fn get_tree_change_seq(state: State<'_, AppState>) -> Result<i64, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM tree_changes", [], |row| row.get(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Nodes inserted, updated, moved or deleted since `since_seq`, collapsed to their current state.
+/// When hoisted to `root_id`, touched nodes outside the subtree are reported as deleted,
+/// so notes that moved out of it drop from the scoped view.
+#[tauri::command]
+pub fn get_tree_changes(
+    state: State<'_, AppState>,
+    since_seq: i64,
+    root_id: Option<String>,
+) -> Result<TreeChanges, String> {
+    let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
+    let root_id_int = parse_root_id(root_id)?;
+
+    let (oldest, latest): (Option<i64>, i64) = conn
+        .query_row("SELECT MIN(seq), COALESCE(MAX(seq), 0) FROM tree_changes", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut changes = TreeChanges {
+        seq: latest,
+        reset: false,
+        inserted: Vec::new(),
+        updated: Vec::new(),
+        moved: Vec::new(),
+        deleted_ids: Vec::new(),
+    };
+
+    let pruned = oldest.is_some_and(|o| since_seq < o - 1);
+    if pruned || since_seq > latest {
+        changes.reset = true;
+        return Ok(changes);
+    }
+
+    // note_id -> strongest kind seen (insert > move > update), in first-seen order
+    let mut touched: Vec<(i64, &'static str)> = Vec::new();
+    let mut index: HashMap<i64, usize> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare("SELECT note_id, kind FROM tree_changes WHERE seq > ? ORDER BY seq")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([since_seq], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (id, kind) = row.map_err(|e| e.to_string())?;
+            let kind = match kind.as_str() {
+                "insert" => "insert",
+                "move" => "move",
+                _ => "update",
+            };
+            match index.get(&id) {
+                Some(&i) => {
+                    let entry = &mut touched[i];
+                    if kind == "insert" || (kind == "move" && entry.1 == "update") {
+                        entry.1 = kind;
+                    }
+                }
+                None => {
+                    index.insert(id, touched.len());
+                    touched.push((id, kind));
+                }
+            }
+        }
+    }
+
+    let scope = scope_ids(&conn, root_id_int)?;
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM notes n WHERE n.id = ? AND n.is_deleted = 0", TREE_NODE_COLUMNS))
+        .map_err(|e| e.to_string())?;
+    for (id, kind) in touched {
+        let mut node = stmt.query_row([id], tree_node_from_row).optional().map_err(|e| e.to_string())?;
+        if scope.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+            node = None;
+        }
+        if let Some(node) = node.as_mut() {
+            detach_root(node, root_id_int);
+        }
+        match (node, kind) {
+            (None, _) => changes.deleted_ids.push(id.to_string()),
+            (Some(node), "insert") => changes.inserted.push(node),
+            (Some(node), "move") => changes.moved.push(node),
+            (Some(node), _) => changes.updated.push(node),
+        }
+    }
+
+    Ok(changes)
+}
+
 #[tauri::command]
 pub fn get_path(state: State<'_, AppState>, note_id: String) -> Result<Vec<String>, String> {
     let conn = state.db.lock().map_err(|_| "Failed to lock database")?;
@@ -1,19 +1,28 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OpenFlags, Result};
 use std::fs;
+use std::path::PathBuf;
 use tauri::AppHandle;
 use tauri::Manager;
 
 const DB_FILENAME: &str = "ultra_memo.db";
 
-pub fn init_db(app: &AppHandle) -> Result<Connection> {
+/// How many `tree_changes` rows are kept; older ones are pruned as new ones arrive.
+const TREE_CHANGES_RETAINED: i64 = 10_000;
+/// Prune the change log every this many inserted rows.
+const TREE_CHANGES_PRUNE_EVERY: i64 = 1_000;
+
+pub(crate) fn db_path(app: &AppHandle) -> PathBuf {
     let app_data_dir = app.path().app_data_dir().expect("failed to get app data dir");
     
     if !app_data_dir.exists() {
         fs::create_dir_all(&app_data_dir).expect("failed to create app data dir");
     }
 
-    let db_path = app_data_dir.join(DB_FILENAME);
-    let mut conn = Connection::open(db_path)?;
+    app_data_dir.join(DB_FILENAME)
+}
+
+pub fn init_db(app: &AppHandle) -> Result<Connection> {
+    let mut conn = Connection::open(db_path(app))?;
 
     // PRAGMA settings for performance and safety
     conn.execute_batch(
@@ -28,6 +37,67 @@ pub fn init_db(app: &AppHandle) -> Result<Connection> {
     Ok(conn)
 }
 
+/// Migrated in-memory database for unit tests, without the welcome note.
+#[cfg(test)]
+pub(crate) fn open_in_memory() -> Result<Connection> {
+    let mut conn = Connection::open_in_memory()?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    migrate(&mut conn)?;
+    conn.execute_batch("DELETE FROM notes; DELETE FROM tree_changes;")?;
+    Ok(conn)
+}
+
+/// Live note for unit tests; timestamps derive from `order_key` and pinned notes are in
+/// editor view, so exporters see distinct metadata per note.
+#[cfg(test)]
+pub(crate) fn insert_test_note(
+    conn: &Connection,
+    parent_id: Option<i64>,
+    title: &str,
+    content: &str,
+    order_key: f64,
+    pinned: bool,
+) -> i64 {
+    conn.execute(
+        "INSERT INTO notes (parent_id, title, content, order_key, is_open, is_pinned, is_markdown_view, is_deleted, created_at, updated_at, structure_updated_at)
+         VALUES (?, ?, ?, ?, 0, ?, ?, 0, ?, ?, 0)",
+        rusqlite::params![parent_id, title, content, order_key, pinned, !pinned, 1_000 + order_key as i64, 2_000 + order_key as i64],
+    )
+    .unwrap();
+    conn.last_insert_rowid()
+}
+
+/// (depth, title, content, pinned, markdown view, created, updated) for every live note in tree order.
+#[cfg(test)]
+pub(crate) fn test_outline(conn: &Connection) -> Vec<(usize, String, String, bool, bool, i64, i64)> {
+    let (notes, start) = crate::commands::export::load_note_rows(conn, None).unwrap();
+    let mut out = Vec::new();
+    crate::commands::export::walk_tree(&notes, start, 0, &mut |note, depth| {
+        out.push((
+            depth,
+            note.title.clone(),
+            note.content.clone(),
+            note.is_pinned,
+            note.is_markdown_view,
+            note.created_at,
+            note.updated_at,
+        ));
+        true
+    });
+    out
+}
+
+/// Read-only connection for search, so long queries never hold the writer's lock.
+/// Must be opened after `init_db` has created and migrated the database.
+pub fn open_search_connection(app: &AppHandle) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        db_path(app),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.execute_batch("PRAGMA temp_store = MEMORY;")?;
+    Ok(conn)
+}
+
 fn migrate(conn: &mut Connection) -> Result<()> {
     let tx = conn.transaction()?;
 
@@ -77,6 +147,12 @@ fn migrate(conn: &mut Connection) -> Result<()> {
         tx.execute("ALTER TABLE notes ADD COLUMN is_markdown_view INTEGER NOT NULL DEFAULT 0", [])?;
     }
 
+    // Migration: Track tree changes (move/reorder) separately from content edits
+    if !columns.contains(&"structure_updated_at".to_string()) {
+        tx.execute("ALTER TABLE notes ADD COLUMN structure_updated_at INTEGER NOT NULL DEFAULT 0", [])?;
+        tx.execute("UPDATE notes SET structure_updated_at = updated_at", [])?;
+    }
+
     // FTS5 Virtual Table
     tx.execute(
         "CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(title, content, content='notes', content_rowid='id')",
@@ -93,6 +169,55 @@ fn migrate(conn: &mut Connection) -> Result<()> {
         [],
     )?;
 
+    // Saved searches (smart folders)
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS saved_searches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            query TEXT NOT NULL,
+            filters TEXT NOT NULL DEFAULT '{}',
+            sort TEXT NOT NULL DEFAULT 'rank',
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Revision history (snapshots taken before bulk rewrites)
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS note_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            note_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_note_revisions_note ON note_revisions (note_id, created_at)",
+        [],
+    )?;
+
+    // Per-parent child sort; parent_key 0 is the root level, manual parents have no row
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS sort_settings (
+            parent_key INTEGER PRIMARY KEY,
+            mode TEXT NOT NULL DEFAULT 'manual',
+            property TEXT,
+            descending INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TRIGGER IF NOT EXISTS notes_sort_settings_ad AFTER DELETE ON notes BEGIN
+            DELETE FROM sort_settings WHERE parent_key = old.id;
+        END;",
+        [],
+    )?;
+
     // Triggers for FTS5 synchronization
     tx.execute(
         "CREATE TRIGGER IF NOT EXISTS notes_ai AFTER INSERT ON notes BEGIN
@@ -114,6 +239,80 @@ fn migrate(conn: &mut Connection) -> Result<()> {
         [],
     )?;
 
+    // Tree change log for incremental snapshots (parents are logged too, since has_children may flip)
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS tree_changes (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            note_id INTEGER NOT NULL,
+            kind TEXT NOT NULL
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TRIGGER IF NOT EXISTS notes_changes_ai AFTER INSERT ON notes BEGIN
+            INSERT INTO tree_changes (note_id, kind) VALUES (new.id, 'insert');
+            INSERT INTO tree_changes (note_id, kind) SELECT new.parent_id, 'update' WHERE new.parent_id IS NOT NULL;
+        END;",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TRIGGER IF NOT EXISTS notes_changes_ad AFTER DELETE ON notes BEGIN
+            INSERT INTO tree_changes (note_id, kind) VALUES (old.id, 'delete');
+            INSERT INTO tree_changes (note_id, kind) SELECT old.parent_id, 'update' WHERE old.parent_id IS NOT NULL;
+        END;",
+        [],
+    )?;
+    // Every column a snapshot or sort mode reads; only open-state toggles stay out of the log
+    tx.execute("DROP TRIGGER IF EXISTS notes_changes_au", [])?;
+    tx.execute(
+        "CREATE TRIGGER notes_changes_au
+         AFTER UPDATE OF parent_id, order_key, title, content, updated_at, is_deleted, is_pinned, is_markdown_view
+         ON notes BEGIN
+            INSERT INTO tree_changes (note_id, kind) VALUES (new.id,
+                CASE
+                    WHEN new.is_deleted = 1 AND old.is_deleted = 0 THEN 'delete'
+                    WHEN new.is_deleted = 0 AND old.is_deleted = 1 THEN 'insert'
+                    WHEN new.parent_id IS NOT old.parent_id OR new.order_key != old.order_key THEN 'move'
+                    ELSE 'update'
+                END);
+            INSERT INTO tree_changes (note_id, kind) SELECT old.parent_id, 'update'
+                WHERE old.parent_id IS NOT NULL
+                  AND (new.parent_id IS NOT old.parent_id OR new.is_deleted != old.is_deleted);
+            INSERT INTO tree_changes (note_id, kind) SELECT new.parent_id, 'update'
+                WHERE new.parent_id IS NOT NULL AND new.parent_id IS NOT old.parent_id;
+        END;",
+        [],
+    )?;
+    // A parent's sort setting reorders its children without touching their rows
+    for (event, row) in [("INSERT", "new"), ("UPDATE", "new"), ("DELETE", "old")] {
+        tx.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS sort_settings_changes_{} AFTER {} ON sort_settings BEGIN
+                    INSERT INTO tree_changes (note_id, kind)
+                        SELECT id, 'move' FROM notes WHERE parent_id IS NULLIF({row}.parent_key, 0) AND is_deleted = 0;
+                END;",
+                event.to_lowercase(),
+                event,
+                row = row
+            ),
+            [],
+        )?;
+    }
+    tx.execute(
+        &format!(
+            "CREATE TRIGGER IF NOT EXISTS tree_changes_prune AFTER INSERT ON tree_changes
+             WHEN new.seq % {} = 0 BEGIN
+                DELETE FROM tree_changes WHERE seq <= new.seq - {};
+            END;",
+            TREE_CHANGES_PRUNE_EVERY, TREE_CHANGES_RETAINED
+        ),
+        [],
+    )?;
+    tx.execute(
+        "DELETE FROM tree_changes WHERE seq <= (SELECT MAX(seq) FROM tree_changes) - ?",
+        [TREE_CHANGES_RETAINED],
+    )?;
+
     // Insert Initial Welcome Note if empty
     let count: i64 = tx.query_row("SELECT COUNT(*) FROM notes", [], |r| r.get(0))?;
     if count == 0 {
@@ -123,13 +322,14 @@ fn migrate(conn: &mut Connection) -> Result<()> {
             .as_millis() as i64;
         
         tx.execute(
-            "INSERT INTO notes (title, content, order_key, is_open, is_deleted, created_at, updated_at) 
-             VALUES (?, ?, ?, 1, 0, ?, ?)",
+            "INSERT INTO notes (title, content, order_key, is_open, is_deleted, created_at, updated_at, structure_updated_at) 
+             VALUES (?, ?, ?, 1, 0, ?, ?, ?)",
             params![
                 "Welcome to Ultra Memo",
                 "# Welcome to Ultra Memo\n\nこれは最強・最高・最速のメモアプリです。\n\n## 主な機能\n- **ツリー構造**: ノートを階層で管理\n- **タブレス**: 2ペイン分割でノートを比較・編集\n- **FTS5 検索**: `Ctrl+P` で瞬時に検索（スニペット表示付き）\n- **CodeMirror 6**: 快適な Markdown 編集と安定した日本語入力\n\n## ショートカット\n- `Ctrl+N`: 同階層に新規ノート\n- `Ctrl+Shift+N`: 子ノートを作成\n- `Ctrl+P`: 検索 (Quick Switcher)\n- `Ctrl+1 / 2`: 左右ペインの切り替え\n- `Enter`: ノートを開く\n- `Ctrl+Enter`: 反対のペインでノートを開く\n\nさあ、ここから最強のメモ体験を始めましょう！",
                 1024.0,
                 now,
+                now,
                 now
             ],
         )?;
@@ -146,3 +346,13 @@ fn migrate(conn: &mut Connection) -> Result<()> {
 }
 
 use rusqlite::params;
+
+/// Snapshot the current title/content of a note into `note_revisions`.
+pub fn record_revision(conn: &Connection, note_id: i64, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO note_revisions (note_id, title, content, created_at)
+         SELECT id, title, content, ? FROM notes WHERE id = ?",
+        params![now, note_id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+/// Split a leading `---` block of `key: value` lines off a note.
+/// Returns the pairs and the remaining body; content without frontmatter comes back whole.
+pub fn parse(content: &str) -> (Vec<(String, String)>, &str) {
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return (Vec::new(), content);
+    };
+
+    let mut pairs = Vec::new();
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line == "---" {
+            return (pairs, &rest[offset..]);
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            pairs.push((key.trim().to_string(), unquote(value.trim())));
+        }
+    }
+
+    // Unterminated block: not frontmatter
+    (Vec::new(), content)
+}
+
+/// Raw lines of the leading block `parse` reads (fences excluded) and the body after it.
+pub fn block(content: &str) -> Option<(&str, &str)> {
+    let (pairs, body) = parse(content);
+    if pairs.is_empty() {
+        return None;
+    }
+    let open = if content.starts_with("---\r\n") { 5 } else { 4 };
+    let fenced = &content[open..content.len() - body.len()];
+    let lines = fenced
+        .strip_suffix("---\r\n")
+        .or_else(|| fenced.strip_suffix("---\n"))
+        .or_else(|| fenced.strip_suffix("---"))
+        .unwrap_or(fenced);
+    Some((lines, body))
+}
+
+/// Value of `key` in the note's frontmatter (keys compare case-insensitively).
+pub fn get(content: &str, key: &str) -> Option<String> {
+    parse(content)
+        .0
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+/// Render `pairs` as a `---` block that `parse` reads back unchanged.
+pub fn render(pairs: &[(&str, String)]) -> String {
+    let mut out = String::from("---\n");
+    for (key, value) in pairs {
+        out.push_str(key);
+        out.push_str(": ");
+        if needs_quotes(value) && !is_list(value) {
+            out.push_str(&serde_json::to_string(value).unwrap_or_default());
+        } else {
+            out.push_str(value);
+        }
+        out.push('\n');
+    }
+    out.push_str("---\n");
+    out
+}
+
+/// `items` as a flow list of double-quoted strings (`["work project", "a,b"]`);
+/// `render` writes it as-is and `list` reads it back item for item.
+pub fn render_list(items: &[String]) -> String {
+    serde_json::to_string(items).unwrap_or_default()
+}
+
+fn is_list(value: &str) -> bool {
+    value.starts_with('[') && serde_json::from_str::<Vec<String>>(value).is_ok()
+}
+
+/// Items of a list value: a `render_list` list, or a hand-written `[a, b]` / `a, b` / `a b`.
+pub fn list(value: &str) -> Vec<String> {
+    if let Ok(items) = serde_json::from_str::<Vec<String>>(value) {
+        return items.into_iter().filter(|item| !item.is_empty()).collect();
+    }
+    value
+        .trim_matches(|c| c == '[' || c == ']')
+        .split([',', ' '])
+        .map(|item| item.trim().trim_matches(['"', '\'']).to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value != value.trim()
+        || value.contains([':', '#', '"', '\'', '\n', '\r'])
+        || value.starts_with(['-', '[', '{', '*', '&', '!', '|', '>', '%', '@', '`'])
+}
+
+/// Double-quoted values use JSON escapes; anything else is taken literally.
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string())
+    } else {
+        value.to_string()
+    }
+}
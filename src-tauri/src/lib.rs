@@ -1,11 +1,20 @@
 mod db;
+mod frontmatter;
+mod naming;
+mod ordering;
+mod snapshots;
 mod commands;
 
-use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
 use tauri::Manager;
 
 pub struct AppState {
   pub db: Mutex<rusqlite::Connection>,
+  /// Separate read-only connection used by search so it never blocks saves.
+  pub search_db: Mutex<rusqlite::Connection>,
+  /// Bumped on every search; an in-flight search aborts once it no longer matches.
+  pub search_generation: Arc<AtomicU64>,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -21,10 +30,17 @@ pub fn run() {
       }
 
       let handle = app.handle();
+      if let Err(e) = snapshots::apply_pending_restore(handle) {
+        log::error!("failed to restore backup: {}", e);
+      }
       let conn = db::init_db(&handle).expect("failed to initialize database");
+      let search_conn = db::open_search_connection(handle).expect("failed to open search connection");
       app.manage(AppState {
         db: Mutex::new(conn),
+        search_db: Mutex::new(search_conn),
+        search_generation: Arc::new(AtomicU64::new(0)),
       });
+      snapshots::start_scheduler(handle.clone());
 
       Ok(())
     })
@@ -39,7 +55,9 @@ pub fn run() {
       commands::notes::toggle_pin_note,
       commands::notes::toggle_markdown_view,
       commands::search::search_notes,
+      commands::search::cancel_search,
       commands::move_note::move_note,
+      commands::move_note::get_sibling_order,
       commands::open::mark_open,
       commands::open::touch_open,
       commands::open::get_open_list,
@@ -47,13 +65,49 @@ pub fn run() {
       commands::trash::restore_note,
       commands::trash::hard_delete_note,
       commands::tree::get_path,
+      commands::tree::get_tree_change_seq,
+      commands::tree::get_tree_changes,
+      commands::tree::get_children,
+      commands::tree::get_subtree,
       commands::export::export_markdown_tree,
+      commands::html::export_html,
+      commands::folder::export_markdown_folder,
+      commands::folder::import_markdown_folder,
+      commands::opml::export_opml,
+      commands::opml::import_opml,
+      commands::enex::import_enex,
+      commands::archive::export_zip_archive,
+      commands::archive::import_zip_archive,
+      commands::json_backup::export_json_backup,
+      commands::json_backup::import_json_backup,
+      commands::backup::backup_database,
+      commands::backup::list_backups,
+      commands::backup::restore_backup,
       commands::notes::create_quick_note,
       commands::notes::create_note_with_title,
       commands::links::resolve_wiki_link,
       commands::links::get_backlinks,
       commands::batch::batch_soft_delete,
       commands::batch::batch_toggle_pin,
+      commands::batch::batch_move,
+      commands::batch::batch_export,
+      commands::duplicate::duplicate_note,
+      commands::merge::merge_notes,
+      commands::split::split_note_by_headings,
+      commands::sort::list_sort_settings,
+      commands::sort::get_sort_setting,
+      commands::sort::set_sort_setting,
+      commands::sort::freeze_sort,
+      commands::replace::preview_find_replace,
+      commands::replace::apply_find_replace,
+      commands::saved_search::create_saved_search,
+      commands::saved_search::list_saved_searches,
+      commands::saved_search::update_saved_search,
+      commands::saved_search::delete_saved_search,
+      commands::saved_search::execute_saved_search,
+      commands::search_index::fts_integrity_check,
+      commands::search_index::rebuild_search_index,
+      commands::search_index::optimize_search_index,
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
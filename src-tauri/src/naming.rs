@@ -0,0 +1,13 @@
+use std::collections::HashSet;
+
+/// `stem`, or `stem (2)`, `stem (3)`… if `used` already holds it (case-insensitively).
+/// The chosen name is added to `used`.
+pub fn unique_name(used: &mut HashSet<String>, stem: String) -> String {
+    let mut name = stem.clone();
+    let mut n = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{} ({})", stem, n);
+        n += 1;
+    }
+    name
+}
@@ -0,0 +1,85 @@
+use rusqlite::{params, Connection, Result};
+
+/// Spacing between keys for appends and after renormalization.
+pub const ORDER_STEP: f64 = 1024.0;
+
+/// When neighbouring keys get closer than this, the parent's children are renumbered.
+const MIN_GAP: f64 = 1e-6;
+
+/// Key halfway between two neighbours (either side may be open).
+pub fn key_between(prev: Option<f64>, next: Option<f64>) -> f64 {
+    match (prev, next) {
+        (Some(p), Some(n)) => (p + n) / 2.0,
+        (Some(p), None) => p + ORDER_STEP,
+        (None, Some(n)) => n - ORDER_STEP,
+        (None, None) => ORDER_STEP,
+    }
+}
+
+/// Ids of the children of `parent_id` in `order_key` order, skipping `exclude`.
+/// Deleted notes are included so they keep their slot if restored.
+pub fn child_ids(conn: &Connection, parent_id: Option<i64>, exclude: &[i64]) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM notes WHERE parent_id IS ? ORDER BY order_key, id")?;
+    let rows = stmt.query_map([parent_id], |row| row.get::<_, i64>(0))?;
+
+    let mut ids = Vec::new();
+    for row in rows {
+        let id = row?;
+        if !exclude.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Renumber the children of `parent_id` to multiples of ORDER_STEP, keeping their order.
+/// Only `order_key` is written.
+pub fn renormalize(conn: &Connection, parent_id: Option<i64>) -> Result<()> {
+    let ids = child_ids(conn, parent_id, &[])?;
+    let mut stmt = conn.prepare("UPDATE notes SET order_key = ? WHERE id = ?")?;
+    for (i, id) in ids.iter().enumerate() {
+        stmt.execute(params![(i as f64 + 1.0) * ORDER_STEP, id])?;
+    }
+    Ok(())
+}
+
+fn order_key_of(conn: &Connection, id: Option<i64>) -> Result<Option<f64>> {
+    match id {
+        Some(id) => conn
+            .query_row("SELECT order_key FROM notes WHERE id = ?", [id], |row| row.get(0))
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// `count` increasing keys strictly between the siblings `prev_id` and `next_id` under `parent_id`.
+/// If the gap is too small the parent's children are renormalized first, in the caller's transaction.
+pub fn keys_between(
+    conn: &Connection,
+    parent_id: Option<i64>,
+    prev_id: Option<i64>,
+    next_id: Option<i64>,
+    count: usize,
+) -> Result<Vec<f64>> {
+    let mut prev = order_key_of(conn, prev_id)?;
+    let mut next = order_key_of(conn, next_id)?;
+
+    if let (Some(p), Some(n)) = (prev, next) {
+        if (n - p) / (count as f64 + 1.0) < MIN_GAP {
+            renormalize(conn, parent_id)?;
+            prev = order_key_of(conn, prev_id)?;
+            next = order_key_of(conn, next_id)?;
+        }
+    }
+
+    let keys = match (prev, next) {
+        (Some(p), Some(n)) => {
+            let gap = (n - p) / (count as f64 + 1.0);
+            (1..=count).map(|i| p + gap * i as f64).collect()
+        }
+        (Some(p), None) => (1..=count).map(|i| p + ORDER_STEP * i as f64).collect(),
+        (None, Some(n)) => (0..count).map(|i| n - ORDER_STEP * (count - i) as f64).collect(),
+        (None, None) => (1..=count).map(|i| ORDER_STEP * i as f64).collect(),
+    };
+    Ok(keys)
+}
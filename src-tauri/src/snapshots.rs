@@ -0,0 +1,222 @@
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri::Manager;
+
+use crate::db::db_path;
+
+const BACKUPS_DIR: &str = "backups";
+/// Holds the file name of a snapshot to swap in on the next start.
+const RESTORE_MARKER: &str = "restore-pending";
+
+const HOUR: i64 = 60 * 60 * 1000;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+
+/// Automatic snapshots are taken when the newest one is older than this.
+const SNAPSHOT_INTERVAL: i64 = HOUR;
+const SCHEDULER_TICK: Duration = Duration::from_secs(10 * 60);
+
+/// Retention tiers: (bucket size, buckets kept). The newest snapshot in each bucket survives.
+const RETENTION: [(i64, i64); 3] = [(HOUR, 24), (DAY, 7), (WEEK, 4)];
+
+pub struct SnapshotFile {
+    pub file_name: String,
+    pub kind: String,
+    pub created_at: i64,
+    pub size: u64,
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+pub fn backups_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(BACKUPS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Copy the live database to `dest` with SQLite's online backup API.
+/// Uses its own read-only connection, so the app's writer is never blocked.
+pub fn backup_to(app: &AppHandle, dest: &Path) -> Result<(), String> {
+    let partial = dest.with_extension("partial");
+    {
+        let src = Connection::open_with_flags(
+            db_path(app),
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| e.to_string())?;
+        let mut dst = Connection::open(&partial).map_err(|e| e.to_string())?;
+        {
+            let backup = Backup::new(&src, &mut dst).map_err(|e| e.to_string())?;
+            // One step copies every page inside a single read transaction, so concurrent
+            // writes can't force the backup to restart
+            loop {
+                match backup.step(-1).map_err(|e| e.to_string())? {
+                    StepResult::Done => break,
+                    _ => std::thread::sleep(Duration::from_millis(50)),
+                }
+            }
+        }
+        // Standalone file: no -wal/-shm next to the snapshot
+        dst.execute_batch("PRAGMA journal_mode = DELETE;").map_err(|e| e.to_string())?;
+    }
+    fs::rename(&partial, dest).map_err(|e| e.to_string())
+}
+
+/// Take a snapshot named `<kind>-<millis>.db` in the backups dir.
+pub fn take_snapshot(app: &AppHandle, kind: &str) -> Result<SnapshotFile, String> {
+    let created_at = now_millis();
+    let file_name = format!("{}-{}.db", kind, created_at);
+    let path = backups_dir(app)?.join(&file_name);
+    backup_to(app, &path)?;
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    Ok(SnapshotFile {
+        file_name,
+        kind: kind.to_string(),
+        created_at,
+        size,
+    })
+}
+
+fn parse_file_name(file_name: &str) -> Option<(String, i64)> {
+    let stem = file_name.strip_suffix(".db")?;
+    let (kind, millis) = stem.rsplit_once('-')?;
+    Some((kind.to_string(), millis.parse().ok()?))
+}
+
+/// Snapshots in the backups dir, newest first.
+pub fn list_snapshots(app: &AppHandle) -> Result<Vec<SnapshotFile>, String> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(backups_dir(app)?).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if let Some((kind, created_at)) = parse_file_name(&file_name) {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            snapshots.push(SnapshotFile {
+                file_name,
+                kind,
+                created_at,
+                size,
+            });
+        }
+    }
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+    Ok(snapshots)
+}
+
+/// Drop automatic snapshots that fall outside every retention tier.
+/// Manual and pre-restore snapshots are only removed by the user.
+fn prune_snapshots(app: &AppHandle, now: i64) -> Result<(), String> {
+    let dir = backups_dir(app)?;
+    let mut buckets: HashSet<(usize, i64)> = HashSet::new();
+    for (i, snapshot) in list_snapshots(app)?.into_iter().filter(|s| s.kind == "auto").enumerate() {
+        let age = now - snapshot.created_at;
+        let mut keep = i == 0;
+        for (tier, (span, count)) in RETENTION.iter().enumerate() {
+            if age < span * count && buckets.insert((tier, snapshot.created_at / span)) {
+                keep = true;
+            }
+        }
+        if !keep {
+            fs::remove_file(dir.join(&snapshot.file_name)).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Background thread taking hourly snapshots and applying the retention policy.
+pub fn start_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let now = now_millis();
+        let latest = list_snapshots(&app)
+            .map(|s| s.iter().filter(|s| s.kind == "auto").map(|s| s.created_at).max())
+            .unwrap_or(None);
+        if latest.map_or(true, |t| now - t >= SNAPSHOT_INTERVAL) {
+            if let Err(e) = take_snapshot(&app, "auto").and_then(|_| prune_snapshots(&app, now)) {
+                log::warn!("automatic snapshot failed: {}", e);
+            }
+        }
+        std::thread::sleep(SCHEDULER_TICK);
+    });
+}
+
+/// Check a snapshot opens and passes `quick_check`, then schedule it to replace the database on next start.
+pub fn request_restore(app: &AppHandle, file_name: &str) -> Result<(), String> {
+    if parse_file_name(file_name).is_none() || file_name.contains(['/', '\\']) {
+        return Err("Invalid backup id".into());
+    }
+    let dir = backups_dir(app)?;
+    let path = dir.join(file_name);
+    if !path.is_file() {
+        return Err("Backup not found".into());
+    }
+
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(|e| e.to_string())?;
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if check != "ok" {
+        return Err(format!("Backup is damaged: {}", check));
+    }
+
+    fs::write(dir.join(RESTORE_MARKER), file_name).map_err(|e| e.to_string())
+}
+
+/// Swap in a snapshot scheduled by `request_restore`. Must run before the database is opened.
+/// The current database is kept as a `pre-restore` snapshot. The marker stays until the
+/// snapshot is in place, so an interrupted restore is retried on the next start.
+pub fn apply_pending_restore(app: &AppHandle) -> Result<(), String> {
+    let dir = backups_dir(app)?;
+    let marker = dir.join(RESTORE_MARKER);
+    let Ok(file_name) = fs::read_to_string(&marker) else {
+        return Ok(());
+    };
+
+    let source = dir.join(file_name.trim());
+    if !source.is_file() {
+        // Nothing to retry with; drop the marker so every start doesn't fail the same way
+        fs::remove_file(&marker).map_err(|e| e.to_string())?;
+        return Err(format!("Backup not found: {}", file_name.trim()));
+    }
+
+    // Copy next to the live file first; the live database is only touched once the copy is complete
+    let live = db_path(app);
+    let mut restoring = live.clone().into_os_string();
+    restoring.push(".restoring");
+    let restoring = PathBuf::from(restoring);
+    if let Err(e) = fs::copy(&source, &restoring) {
+        let _ = fs::remove_file(&restoring);
+        return Err(e.to_string());
+    }
+
+    if live.exists() {
+        // Fold any leftover WAL into the main file before moving it aside
+        {
+            let conn = Connection::open(&live).map_err(|e| e.to_string())?;
+            conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);").map_err(|e| e.to_string())?;
+        }
+        let aside = dir.join(format!("pre-restore-{}.db", now_millis()));
+        fs::rename(&live, &aside).map_err(|e| e.to_string())?;
+        for suffix in ["-wal", "-shm"] {
+            let mut side = live.clone().into_os_string();
+            side.push(suffix);
+            let _ = fs::remove_file(PathBuf::from(side));
+        }
+    }
+
+    fs::rename(&restoring, &live).map_err(|e| e.to_string())?;
+    fs::remove_file(&marker).map_err(|e| e.to_string())
+}